@@ -0,0 +1,72 @@
+use crate::protocol::{
+    apply_request_range, content_response, guess_mime_type, request_entry_name, status_response,
+    ProtocolResponse,
+};
+use std::io::{Read, Seek};
+use std::sync::Mutex;
+use wry::http::{Request, StatusCode};
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+const INDEX_FILE: &str = "index.html";
+
+pub struct ArchiveProtocol<R: Read + Seek> {
+    archive: Mutex<ZipArchive<R>>,
+}
+
+impl<R: Read + Seek> ArchiveProtocol<R> {
+    pub fn new(reader: R) -> zip::result::ZipResult<Self> {
+        Ok(Self {
+            archive: Mutex::new(ZipArchive::new(reader)?),
+        })
+    }
+
+    pub fn handle(&self, request: &Request<Vec<u8>>) -> ProtocolResponse {
+        self.resolve(request.uri().path())
+            .and_then(|entry_name| {
+                self.read_entry(&entry_name)
+                    .map(|content| content_response(content, &guess_mime_type(&entry_name)))
+            })
+            .map(|response| apply_request_range(request.headers(), response))
+            .unwrap_or_else(status_response)
+    }
+
+    fn resolve(&self, uri_path: &str) -> Result<String, StatusCode> {
+        let entry_name = request_entry_name(uri_path)?;
+
+        let archive = self.archive.lock().unwrap();
+
+        if !entry_name.is_empty() && archive.index_for_name(&entry_name).is_some() {
+            return Ok(entry_name);
+        }
+
+        let index_name = if entry_name.is_empty() {
+            INDEX_FILE.to_string()
+        } else {
+            format!("{}/{}", entry_name, INDEX_FILE)
+        };
+
+        archive
+            .index_for_name(&index_name)
+            .map(|_| index_name)
+            .ok_or(StatusCode::NOT_FOUND)
+    }
+
+    fn read_entry(&self, entry_name: &str) -> Result<Vec<u8>, StatusCode> {
+        let mut archive = self.archive.lock().unwrap();
+        let mut entry = archive.by_name(entry_name).map_err(|error| match error {
+            ZipError::FileNotFound => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
+
+        if entry.is_dir() {
+            return Err(StatusCode::NOT_FOUND);
+        }
+
+        let mut content = Vec::with_capacity(entry.size() as usize);
+        entry
+            .read_to_end(&mut content)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        Ok(content)
+    }
+}
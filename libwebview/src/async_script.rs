@@ -0,0 +1,166 @@
+use crate::script::ScriptToEvaluate;
+use crate::script_exception::{
+    eval_blocked_check, is_eval_blocked, EvalState, ScriptException, DESCRIBE_EXCEPTION_FUNCTION,
+};
+use crate::trusted_origins::random_nonce;
+use crate::webview::{evaluate_script_without_eval, WebView};
+use serde_json::Value;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+const ASYNC_SCRIPT_KEY: &str = "__libwebview_script";
+
+/// Evaluates scripts that may return a Promise and settles them once the Promise is.
+/// Outcomes are posted back over IPC, so the ipc handler must let them through `settle`.
+/// Each evaluation has its own nonce, only known to the evaluated script, so that pages can
+/// not settle it. The outcome is serialized and posted with functions captured by
+/// the origin stamp script before page scripts run
+#[derive(Clone, Default)]
+pub struct AsyncScripts(Rc<AsyncScriptsData>);
+
+#[derive(Default)]
+struct AsyncScriptsData {
+    next_id: Cell<u64>,
+    pending: RefCell<HashMap<u64, PendingScript>>,
+}
+
+struct PendingScript {
+    nonce: String,
+    script: ScriptToEvaluate,
+}
+
+impl AsyncScripts {
+    /// On pages that block `eval` the script is evaluated as is and its value is not awaited
+    pub fn evaluate(&self, webview: &WebView, script: &ScriptToEvaluate) -> wry::Result<()> {
+        let eval_support = webview.eval_support();
+        let (page_load, eval_state) = eval_support.get();
+        if eval_state == EvalState::Blocked {
+            evaluate_script_without_eval(webview.key(), script.clone());
+            return Ok(());
+        }
+        let is_probing = eval_state == EvalState::Unknown;
+
+        let id = self.0.next_id.get() + 1;
+        self.0.next_id.set(id);
+        let nonce = random_nonce();
+
+        // scripts that timed out or were cancelled will never be settled over IPC
+        self.0
+            .pending
+            .borrow_mut()
+            .retain(|_, pending| pending.script.is_pending());
+        self.0.pending.borrow_mut().insert(
+            id,
+            PendingScript {
+                nonce: nonce.clone(),
+                script: script.clone(),
+            },
+        );
+
+        let script_clone = script.clone();
+        let webview_key = webview.key();
+        webview
+            .evaluate_script_with_callback(
+                &async_script(&nonce, id, script.script(), is_probing),
+                move |value| {
+                    if is_eval_blocked(&value) {
+                        eval_support.set(page_load, true);
+                        evaluate_script_without_eval(webview_key, script_clone.clone());
+                    } else if is_probing && !value.is_empty() {
+                        eval_support.set(page_load, false);
+                    }
+                },
+            )
+            .inspect_err(|_| {
+                self.0.pending.borrow_mut().remove(&id);
+            })
+    }
+
+    /// Returns true if the message is the outcome of an async script
+    pub fn settle(&self, message: &str) -> bool {
+        if !message.contains(ASYNC_SCRIPT_KEY) {
+            return false;
+        }
+        let Ok(message) = serde_json::from_str::<Value>(message) else {
+            return false;
+        };
+        let Some(outcome) = message.get(ASYNC_SCRIPT_KEY) else {
+            return false;
+        };
+        let Some(id) = outcome.get("id").and_then(Value::as_u64) else {
+            return false;
+        };
+        let nonce = outcome.get("nonce").and_then(Value::as_str);
+        let script = {
+            let mut pending = self.0.pending.borrow_mut();
+            match pending.get(&id) {
+                Some(script) if Some(script.nonce.as_str()) == nonce => {
+                    pending.remove(&id).map(|pending| pending.script)
+                }
+                _ => return false,
+            }
+        };
+        if let Some(script) = script {
+            let is_resolved = outcome
+                .get("isResolved")
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+            let value = message.get("value").cloned().unwrap_or(Value::Null);
+            if is_resolved {
+                script.set_value(value.to_string());
+            } else {
+                script.set_exception(ScriptException::from_json(&value));
+            }
+        }
+        true
+    }
+}
+
+/// The script is evaluated globally, like a synchronous one, and its completion value awaited.
+/// The nonce is only part of a string, pages can not reach it while the value is serialized
+fn async_script(nonce: &str, id: u64, script: &str, is_probing: bool) -> String {
+    let prefix = format!(
+        r#"{{"{key}":{{"nonce":{nonce},"id":{id},"isResolved":"#,
+        key = ASYNC_SCRIPT_KEY,
+        nonce = Value::from(nonce),
+        id = id
+    );
+    format!(
+        r#"(function () {{
+    "use strict";
+    {eval_blocked_check}
+    var ipc = window.__libwebview_ipc;
+    if (!ipc) {{
+        return null;
+    }}
+    var prefix = {prefix};
+    function post(isResolved, value) {{
+        var serialized = ipc.serialize(value === undefined ? null : value);
+        var outcome = isResolved ? "true" : "false";
+        ipc.postMessage(prefix + outcome + "}},\"value\":" + serialized + "}}");
+    }}
+    var describe = {describe};
+    Promise.resolve()
+        .then(function () {{ return (0, eval)({script}); }})
+        .then(function (value) {{
+            try {{
+                post(true, value);
+            }} catch (error) {{
+                post(false, describe(error));
+            }}
+        }}, function (error) {{
+            post(false, describe(error));
+        }});
+    return null;
+}})();"#,
+        eval_blocked_check = if is_probing {
+            eval_blocked_check()
+        } else {
+            String::new()
+        },
+        prefix = Value::from(prefix),
+        script = Value::from(script),
+        describe = DESCRIBE_EXCEPTION_FUNCTION
+    )
+}
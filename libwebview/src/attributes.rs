@@ -1,23 +1,302 @@
-use crate::events_handler::{EventsHandler, WebViewId};
+use crate::archive_protocol::ArchiveProtocol;
+use crate::async_script::AsyncScripts;
+use crate::directory_protocol::DirectoryProtocol;
+use crate::downloads::DownloadDestinations;
+use crate::events_handler::{EventsHandler, WebViewEventType, WebViewId};
+use crate::navigation_decision::HostNavigationDecider;
+use crate::navigation_policy::{NavigationPolicy, NavigationRule, NavigationRuleKind};
+use crate::new_window::{NewWindowPolicy, NewWindowRequest, SameWebViewLoader, NEW_WINDOW_SCRIPT};
+use crate::page_messages::{PageMessenger, MESSAGE_BRIDGE_SCRIPT};
+use crate::protocol::{apply_request_range, ProtocolResponder};
+use crate::resource_table::ResourceTable;
+use crate::rpc::RPC_BRIDGE_SCRIPT;
+use crate::script_exception::EvalSupport;
+use crate::trusted_origins::{uri_origin, OriginStamps, TrustedOrigins};
 use anyhow::anyhow;
 use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::Cursor;
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::rc::Rc;
 use std::str::FromStr;
 use string_box::StringBox;
 use value_box::{ReturnBoxerResult, ValueBox, ValueBoxPointer};
 use wry::dpi::{LogicalPosition, Position, Size};
-use wry::http::{HeaderMap, HeaderName, HeaderValue, Response, StatusCode};
-use wry::{dpi, Rect, WebViewAttributes};
+use wry::http::{HeaderMap, HeaderName, HeaderValue, Request, Response, StatusCode};
+use wry::{dpi, DragDropEvent, PageLoadEvent, Rect};
+
+pub struct WebViewAttributes {
+    attributes: wry::WebViewAttributes<'static>,
+    events_handler: Option<(EventsHandler, WebViewId)>,
+    navigation_policy: NavigationPolicy,
+    navigation_decided_by_host: bool,
+    new_window_policy: NewWindowPolicy,
+    same_webview_loader: SameWebViewLoader,
+    download_destinations: DownloadDestinations,
+    is_drag_drop_blocked: bool,
+    trusted_origins: TrustedOrigins,
+    origin_stamps: OriginStamps,
+    page_messenger: PageMessenger,
+    async_scripts: AsyncScripts,
+    eval_support: EvalSupport,
+}
+
+impl WebViewAttributes {
+    /// The decider must be connected to the built webview, navigation is then not handled by wry
+    pub fn host_navigation_decider(&self) -> Option<HostNavigationDecider> {
+        if !self.navigation_decided_by_host {
+            return None;
+        }
+        self.events_handler
+            .as_ref()
+            .map(|(events_handler, webview_id)| {
+                HostNavigationDecider::new(
+                    events_handler.clone(),
+                    *webview_id,
+                    self.navigation_policy.clone(),
+                )
+            })
+    }
+
+    /// The loader must be attached to the built webview
+    pub fn same_webview_loader(&self) -> SameWebViewLoader {
+        self.same_webview_loader.clone()
+    }
+
+    /// The messenger must be attached to the built webview
+    pub fn page_messenger(&self) -> PageMessenger {
+        self.page_messenger.clone()
+    }
+
+    pub fn async_scripts(&self) -> AsyncScripts {
+        self.async_scripts.clone()
+    }
+
+    pub fn eval_support(&self) -> EvalSupport {
+        self.eval_support.clone()
+    }
+
+    pub fn into_wry_attributes(self) -> wry::WebViewAttributes<'static> {
+        let is_navigation_decided_by_host = self.host_navigation_decider().is_some();
+        let Self {
+            mut attributes,
+            events_handler,
+            navigation_policy,
+            new_window_policy,
+            same_webview_loader,
+            download_destinations,
+            is_drag_drop_blocked,
+            trusted_origins,
+            origin_stamps,
+            page_messenger,
+            async_scripts,
+            eval_support,
+            ..
+        } = self;
+
+        // run in all frames, before any other script
+        attributes
+            .initialization_scripts
+            .insert(0, (origin_stamps.script(), false));
+        attributes
+            .initialization_scripts
+            .insert(1, (NEW_WINDOW_SCRIPT.to_string(), false));
+
+        // new windows are requested either by the webview or, with features, over IPC
+        let handler_for_new_window = events_handler.clone();
+        let open_new_window = Rc::new(move |url: String, features: String| {
+            let applied_policy = match new_window_policy {
+                NewWindowPolicy::LoadInSameWebView if !same_webview_loader.load(&url) => {
+                    NewWindowPolicy::DecidedByHost
+                }
+                policy => policy,
+            };
+            if let Some((events_handler, webview_id)) = &handler_for_new_window {
+                events_handler.enqueue_new_window_requested(
+                    *webview_id,
+                    url,
+                    features,
+                    applied_policy,
+                );
+            }
+        });
+
+        // async script outcomes and new windows are always handled, events only with
+        // an events handler. All are only accepted from trusted origins
+        let handler_for_ipc = events_handler.clone();
+        let open_new_window_for_ipc = open_new_window.clone();
+        attributes.ipc_handler = Some(Box::new(move |request: Request<String>| {
+            let (stamped_origin, request) = match origin_stamps.unstamp(request.body()) {
+                Some((origin, message)) => {
+                    let (parts, _) = request.into_parts();
+                    (Some(origin), Request::from_parts(parts, message))
+                }
+                None => (None, request),
+            };
+            if !trusted_origins.is_trusted(stamped_origin.as_deref()) {
+                if let Some((events_handler, _)) = &handler_for_ipc {
+                    events_handler.drop_untrusted_request();
+                }
+                return;
+            }
+            if async_scripts.settle(request.body()) {
+                return;
+            }
+            if let Some(new_window) = NewWindowRequest::parse(request.body()) {
+                open_new_window_for_ipc(new_window.url, new_window.features);
+                return;
+            }
+            if let Some((events_handler, webview_id)) = &handler_for_ipc {
+                let origin = stamped_origin.unwrap_or_else(|| uri_origin(request.uri()));
+                events_handler.enqueue_request(*webview_id, origin, request);
+            }
+        }));
+
+        let handler_for_loading = events_handler.clone();
+        attributes.on_page_load_handler = Some(Box::new(move |event, url| {
+            page_messenger.page_load(&event, &url);
+            if let PageLoadEvent::Started = event {
+                eval_support.page_started();
+            }
+            if let Some((events_handler, webview_id)) = &handler_for_loading {
+                events_handler.enqueue_page_load(*webview_id, event, url);
+            }
+        }));
+
+        if events_handler.is_some() || is_drag_drop_blocked {
+            let handler_for_drag_drop = events_handler.clone();
+            let dragged_paths = RefCell::new(Vec::<PathBuf>::new());
+            attributes.drag_drop_handler = Some(Box::new(move |event| {
+                if let Some((events_handler, webview_id)) = &handler_for_drag_drop {
+                    // only enter and drop report paths, remember them for the other events
+                    let (event_type, position) = match event {
+                        DragDropEvent::Enter { paths, position } => {
+                            *dragged_paths.borrow_mut() = paths;
+                            (WebViewEventType::DragEnter, position)
+                        }
+                        DragDropEvent::Over { position } => (WebViewEventType::DragOver, position),
+                        DragDropEvent::Drop { paths, position } => {
+                            *dragged_paths.borrow_mut() = paths;
+                            (WebViewEventType::Drop, position)
+                        }
+                        _ => (WebViewEventType::DragLeave, (0, 0)),
+                    };
+                    // avoids copying the paths of events the handler is not subscribed to
+                    if events_handler.is_enabled(event_type) {
+                        events_handler.enqueue_drag_drop(
+                            *webview_id,
+                            event_type,
+                            dragged_paths.borrow().clone(),
+                            position,
+                        );
+                    }
+                }
+                is_drag_drop_blocked
+            }));
+        }
+
+        if events_handler.is_some() || !download_destinations.is_empty() {
+            let handler_for_download_started = events_handler.clone();
+            attributes.download_started_handler = Some(Box::new(move |url, destination| {
+                let suggested_filename = download_destinations.apply(&url, destination);
+                if let Some((events_handler, webview_id)) = &handler_for_download_started {
+                    events_handler.enqueue_download_started(
+                        *webview_id,
+                        url,
+                        suggested_filename,
+                        destination.clone(),
+                    );
+                }
+                true
+            }));
+        }
+
+        if let Some((events_handler, webview_id)) = events_handler.clone() {
+            attributes.download_completed_handler =
+                Some(Rc::new(move |url, destination, is_success| {
+                    events_handler.enqueue_download_completed(
+                        webview_id,
+                        url,
+                        destination,
+                        is_success,
+                    );
+                }));
+        }
+
+        // always installed, otherwise some platforms open a native window
+        attributes.new_window_req_handler = Some(Box::new(move |url| {
+            open_new_window(url, String::new());
+            // a native window is never opened
+            false
+        }));
+
+        if let Some((events_handler, webview_id)) = events_handler {
+            if !is_navigation_decided_by_host {
+                let handler_for_navigation = events_handler.clone();
+                attributes.navigation_handler = Some(Box::new(move |url| {
+                    if navigation_policy.is_allowed(&url) {
+                        handler_for_navigation.enqueue_navigation(webview_id, url);
+                        true
+                    } else {
+                        handler_for_navigation.enqueue_navigation_blocked(webview_id, url);
+                        false
+                    }
+                }));
+            }
+            let handler_for_title = events_handler.clone();
+            attributes.document_title_changed_handler = Some(Box::new(move |title| {
+                handler_for_title.enqueue_title_changed(webview_id, title);
+            }))
+        } else if !navigation_policy.is_empty() {
+            attributes.navigation_handler =
+                Some(Box::new(move |url| navigation_policy.is_allowed(&url)));
+        }
+
+        attributes
+    }
+}
+
+impl Deref for WebViewAttributes {
+    type Target = wry::WebViewAttributes<'static>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.attributes
+    }
+}
+
+impl DerefMut for WebViewAttributes {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.attributes
+    }
+}
 
 #[no_mangle]
-pub extern "C" fn webview_attributes_default() -> *mut ValueBox<WebViewAttributes<'static>> {
-    let mut attributes = WebViewAttributes::default();
+pub extern "C" fn webview_attributes_default() -> *mut ValueBox<WebViewAttributes> {
+    let mut attributes = wry::WebViewAttributes::default();
     attributes.focused = false;
-    ValueBox::new(attributes).into_raw()
+    ValueBox::new(WebViewAttributes {
+        attributes,
+        events_handler: None,
+        navigation_policy: Default::default(),
+        navigation_decided_by_host: false,
+        new_window_policy: Default::default(),
+        same_webview_loader: Default::default(),
+        download_destinations: Default::default(),
+        is_drag_drop_blocked: false,
+        trusted_origins: Default::default(),
+        origin_stamps: Default::default(),
+        page_messenger: Default::default(),
+        async_scripts: Default::default(),
+        eval_support: Default::default(),
+    })
+    .into_raw()
 }
 
 #[no_mangle]
 pub extern "C" fn webview_attributes_set_url(
-    attributes: *mut ValueBox<WebViewAttributes<'static>>,
+    attributes: *mut ValueBox<WebViewAttributes>,
     url: *mut ValueBox<StringBox>,
 ) {
     attributes
@@ -35,7 +314,7 @@ pub extern "C" fn webview_attributes_set_url(
 
 #[no_mangle]
 pub extern "C" fn webview_attributes_set_html(
-    attributes: *mut ValueBox<WebViewAttributes<'static>>,
+    attributes: *mut ValueBox<WebViewAttributes>,
     html: *mut ValueBox<StringBox>,
 ) {
     attributes
@@ -49,7 +328,7 @@ pub extern "C" fn webview_attributes_set_html(
 
 #[no_mangle]
 pub extern "C" fn webview_attributes_add_header(
-    attributes: *mut ValueBox<WebViewAttributes<'static>>,
+    attributes: *mut ValueBox<WebViewAttributes>,
     header_name: *mut ValueBox<StringBox>,
     header_value: *mut ValueBox<StringBox>,
 ) -> bool {
@@ -80,7 +359,7 @@ pub extern "C" fn webview_attributes_add_header(
 
 #[no_mangle]
 pub extern "C" fn webview_attributes_add_custom_protocol(
-    attributes: *mut ValueBox<WebViewAttributes<'static>>,
+    attributes: *mut ValueBox<WebViewAttributes>,
     protocol_name: *mut ValueBox<StringBox>,
     content: *mut ValueBox<StringBox>,
 ) {
@@ -91,13 +370,147 @@ pub extern "C" fn webview_attributes_add_custom_protocol(
                     let content = Vec::from(content.as_bytes());
                     attributes.custom_protocols.insert(
                         protocol_name.to_string(),
-                        Box::new(move |_webview_id, _request, responder| {
-                            responder.respond(
+                        Box::new(move |_webview_id, request, responder| {
+                            responder.respond(apply_request_range(
+                                request.headers(),
                                 Response::builder()
                                     .status(StatusCode::OK)
                                     .body(Cow::Owned(content.clone()))
                                     .unwrap(),
-                            )
+                            ))
+                        }),
+                    );
+                })
+            })
+        })
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_attributes_add_directory_protocol(
+    attributes: *mut ValueBox<WebViewAttributes>,
+    protocol_name: *mut ValueBox<StringBox>,
+    root_directory: *mut ValueBox<StringBox>,
+) {
+    attributes
+        .with_mut(|attributes| {
+            protocol_name.with_ref(|protocol_name| {
+                root_directory.with_ref_ok(|root_directory| {
+                    let protocol = DirectoryProtocol::new(root_directory.as_str());
+                    attributes.custom_protocols.insert(
+                        protocol_name.to_string(),
+                        Box::new(move |_webview_id, request, responder| {
+                            responder.respond(protocol.handle(&request))
+                        }),
+                    );
+                })
+            })
+        })
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_attributes_add_archive_protocol_from_file(
+    attributes: *mut ValueBox<WebViewAttributes>,
+    protocol_name: *mut ValueBox<StringBox>,
+    archive_path: *mut ValueBox<StringBox>,
+) -> bool {
+    attributes
+        .with_mut(|attributes| {
+            protocol_name.with_ref(|protocol_name| {
+                archive_path.with_ref(|archive_path| {
+                    File::open(archive_path.as_str())
+                        .map_err(|error| anyhow!(error).into())
+                        .and_then(|file| {
+                            ArchiveProtocol::new(file).map_err(|error| anyhow!(error).into())
+                        })
+                        .map(|protocol| {
+                            attributes.custom_protocols.insert(
+                                protocol_name.to_string(),
+                                Box::new(move |_webview_id, request, responder| {
+                                    responder.respond(protocol.handle(&request))
+                                }),
+                            );
+                            true
+                        })
+                })
+            })
+        })
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_attributes_add_archive_protocol_from_bytes(
+    attributes: *mut ValueBox<WebViewAttributes>,
+    protocol_name: *mut ValueBox<StringBox>,
+    archive_bytes: *const u8,
+    archive_length: usize,
+) -> bool {
+    attributes
+        .with_mut(|attributes| {
+            protocol_name.with_ref(|protocol_name| {
+                let bytes = if archive_bytes.is_null() || archive_length == 0 {
+                    Vec::new()
+                } else {
+                    unsafe { std::slice::from_raw_parts(archive_bytes, archive_length) }.to_vec()
+                };
+                ArchiveProtocol::new(Cursor::new(bytes))
+                    .map_err(|error| anyhow!(error).into())
+                    .map(|protocol| {
+                        attributes.custom_protocols.insert(
+                            protocol_name.to_string(),
+                            Box::new(move |_webview_id, request, responder| {
+                                responder.respond(protocol.handle(&request))
+                            }),
+                        );
+                        true
+                    })
+            })
+        })
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_attributes_add_resource_table_protocol(
+    attributes: *mut ValueBox<WebViewAttributes>,
+    protocol_name: *mut ValueBox<StringBox>,
+    resource_table: *mut ValueBox<ResourceTable>,
+) {
+    attributes
+        .with_mut(|attributes| {
+            protocol_name.with_ref(|protocol_name| {
+                resource_table.with_clone_ok(|resource_table| {
+                    attributes.custom_protocols.insert(
+                        protocol_name.to_string(),
+                        Box::new(move |_webview_id, request, responder| {
+                            responder.respond(resource_table.handle(&request))
+                        }),
+                    );
+                })
+            })
+        })
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_attributes_add_custom_protocol_handler(
+    attributes: *mut ValueBox<WebViewAttributes>,
+    protocol_name: *mut ValueBox<StringBox>,
+    events_handler: *mut ValueBox<EventsHandler>,
+    webview_id: WebViewId,
+) {
+    attributes
+        .with_mut(|attributes| {
+            protocol_name.with_ref(|protocol_name| {
+                events_handler.with_clone_ok(|events_handler| {
+                    attributes.custom_protocols.insert(
+                        protocol_name.to_string(),
+                        Box::new(move |_webview_id, request, responder| {
+                            events_handler.enqueue_protocol_request(
+                                webview_id,
+                                request,
+                                ProtocolResponder::new(responder),
+                            );
                         }),
                     );
                 })
@@ -108,34 +521,158 @@ pub extern "C" fn webview_attributes_add_custom_protocol(
 
 #[no_mangle]
 pub extern "C" fn webview_attributes_set_events_handler(
-    attributes: *mut ValueBox<WebViewAttributes<'static>>,
+    attributes: *mut ValueBox<WebViewAttributes>,
     events_handler: *mut ValueBox<EventsHandler>,
     webview_id: WebViewId,
 ) {
     attributes
         .with_mut(|attributes| {
-            events_handler.with_clone_ok(|event_handler| {
-                let handler_for_ipc = event_handler.clone();
-                attributes.ipc_handler = Some(Box::new(move |request| {
-                    handler_for_ipc.enqueue_request(webview_id, request);
-                }));
-                let handler_for_navigation = event_handler.clone();
-                attributes.navigation_handler = Some(Box::new(move |url| {
-                    handler_for_navigation.enqueue_navigation(webview_id, url);
-                    true
-                }));
-                let handler_for_loading = event_handler.clone();
-                attributes.on_page_load_handler = Some(Box::new(move |event, url| {
-                    handler_for_loading.enqueue_page_load(webview_id, event, url);
-                }))
+            events_handler.with_clone_ok(|events_handler| {
+                attributes.events_handler = Some((events_handler, webview_id));
             })
         })
         .log();
 }
 
+#[no_mangle]
+pub extern "C" fn webview_attributes_allow_navigation(
+    attributes: *mut ValueBox<WebViewAttributes>,
+    rule_kind: u8,
+    pattern: *mut ValueBox<StringBox>,
+) -> bool {
+    attributes
+        .with_mut(|attributes| {
+            pattern.with_ref(|pattern| {
+                let rule_kind =
+                    NavigationRuleKind::try_from(rule_kind).map_err(|error| anyhow!(error))?;
+                NavigationRule::new(rule_kind, pattern.as_str())
+                    .map_err(|error| anyhow!(error).into())
+                    .map(|rule| {
+                        attributes.navigation_policy.allow(rule);
+                        true
+                    })
+            })
+        })
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_attributes_deny_navigation(
+    attributes: *mut ValueBox<WebViewAttributes>,
+    rule_kind: u8,
+    pattern: *mut ValueBox<StringBox>,
+) -> bool {
+    attributes
+        .with_mut(|attributes| {
+            pattern.with_ref(|pattern| {
+                let rule_kind =
+                    NavigationRuleKind::try_from(rule_kind).map_err(|error| anyhow!(error))?;
+                NavigationRule::new(rule_kind, pattern.as_str())
+                    .map_err(|error| anyhow!(error).into())
+                    .map(|rule| {
+                        attributes.navigation_policy.deny(rule);
+                        true
+                    })
+            })
+        })
+        .or_log(false)
+}
+
+/// Returns false if the platform can not hold navigation requests pending
+#[no_mangle]
+pub extern "C" fn webview_attributes_set_navigation_decided_by_host(
+    attributes: *mut ValueBox<WebViewAttributes>,
+    is_decided_by_host: bool,
+) -> bool {
+    attributes
+        .with_mut_ok(|attributes| {
+            let is_supported = HostNavigationDecider::is_supported();
+            attributes.navigation_decided_by_host = is_decided_by_host && is_supported;
+            is_supported || !is_decided_by_host
+        })
+        .or_log(false)
+}
+
+/// Requests that can not be loaded in the same webview are left to the host
+#[no_mangle]
+pub extern "C" fn webview_attributes_set_new_window_policy(
+    attributes: *mut ValueBox<WebViewAttributes>,
+    policy: u8,
+) -> bool {
+    attributes
+        .with_mut(|attributes| {
+            attributes.new_window_policy =
+                NewWindowPolicy::try_from(policy).map_err(|error| anyhow!(error))?;
+            Ok(true)
+        })
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_attributes_set_download_directory(
+    attributes: *mut ValueBox<WebViewAttributes>,
+    directory: *mut ValueBox<StringBox>,
+) {
+    attributes
+        .with_mut(|attributes| {
+            directory.with_ref_ok(|directory| {
+                attributes
+                    .download_destinations
+                    .set_directory(directory.as_str())
+            })
+        })
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_attributes_set_download_destination(
+    attributes: *mut ValueBox<WebViewAttributes>,
+    url: *mut ValueBox<StringBox>,
+    destination: *mut ValueBox<StringBox>,
+) {
+    attributes
+        .with_mut(|attributes| {
+            url.with_ref(|url| {
+                destination.with_ref_ok(|destination| {
+                    attributes
+                        .download_destinations
+                        .set_destination(url.as_str(), destination.as_str())
+                })
+            })
+        })
+        .log();
+}
+
+/// Blocking the default behaviour also prevents dropping files on `<input type="file">`
+#[no_mangle]
+pub extern "C" fn webview_attributes_set_drag_drop_blocked(
+    attributes: *mut ValueBox<WebViewAttributes>,
+    is_blocked: bool,
+) {
+    attributes
+        .with_mut_ok(|attributes| attributes.is_drag_drop_blocked = is_blocked)
+        .log();
+}
+
+/// Once an origin is trusted, IPC messages are only accepted from trusted origins.
+/// Their origin is only known for messages posted with `window.host.postMessage`, so pages
+/// must post with it: messages posted with `window.ipc.postMessage` are then dropped,
+/// even those of trusted pages. Dropped messages are counted by the events handler
+#[no_mangle]
+pub extern "C" fn webview_attributes_add_trusted_origin(
+    attributes: *mut ValueBox<WebViewAttributes>,
+    origin: *mut ValueBox<StringBox>,
+) {
+    attributes
+        .with_mut(|attributes| {
+            origin.with_ref_ok(|origin| attributes.trusted_origins.add(origin.as_str()))
+        })
+        .log();
+}
+
 #[no_mangle]
 pub extern "C" fn webview_attributes_set_position(
-    attributes: *mut ValueBox<WebViewAttributes<'static>>,
+    attributes: *mut ValueBox<WebViewAttributes>,
     x: f64,
     y: f64,
 ) {
@@ -161,7 +698,7 @@ pub extern "C" fn webview_attributes_set_position(
 
 #[no_mangle]
 pub extern "C" fn webview_attributes_add_initial_script(
-    attributes: *mut ValueBox<WebViewAttributes<'static>>,
+    attributes: *mut ValueBox<WebViewAttributes>,
     script: *mut ValueBox<StringBox>,
 ) {
     script
@@ -175,9 +712,33 @@ pub extern "C" fn webview_attributes_add_initial_script(
         .log();
 }
 
+#[no_mangle]
+pub extern "C" fn webview_attributes_add_rpc_bridge(attributes: *mut ValueBox<WebViewAttributes>) {
+    attributes
+        .with_mut_ok(|attributes| {
+            attributes
+                .initialization_scripts
+                .push((RPC_BRIDGE_SCRIPT.to_string(), true))
+        })
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_attributes_add_message_bridge(
+    attributes: *mut ValueBox<WebViewAttributes>,
+) {
+    attributes
+        .with_mut_ok(|attributes| {
+            attributes
+                .initialization_scripts
+                .push((MESSAGE_BRIDGE_SCRIPT.to_string(), true))
+        })
+        .log();
+}
+
 #[no_mangle]
 pub extern "C" fn webview_attributes_set_size(
-    attributes: *mut ValueBox<WebViewAttributes<'static>>,
+    attributes: *mut ValueBox<WebViewAttributes>,
     width: f64,
     height: f64,
 ) {
@@ -202,8 +763,6 @@ pub extern "C" fn webview_attributes_set_size(
 }
 
 #[no_mangle]
-pub extern "C" fn webview_attributes_release(
-    attributes: *mut ValueBox<WebViewAttributes<'static>>,
-) {
+pub extern "C" fn webview_attributes_release(attributes: *mut ValueBox<WebViewAttributes>) {
     attributes.release();
 }
@@ -0,0 +1,97 @@
+use crate::protocol::{
+    content_response, guess_mime_type, partial_response, relative_request_path, status_response,
+    unsatisfiable_range_response, ProtocolResponse, RequestedRange,
+};
+use std::fs::File;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use wry::http::{HeaderMap, Request, StatusCode};
+
+const INDEX_FILE: &str = "index.html";
+
+#[derive(Debug, Clone)]
+pub struct DirectoryProtocol {
+    root: PathBuf,
+}
+
+impl DirectoryProtocol {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn handle(&self, request: &Request<Vec<u8>>) -> ProtocolResponse {
+        self.resolve(request.uri().path())
+            .and_then(|file| {
+                Self::read_file(&file, request.headers())
+                    .map_err(|error| status_for_io_error(error.kind()))
+            })
+            .unwrap_or_else(status_response)
+    }
+
+    /// Only the requested range is read, so that large media files can be seeked cheaply
+    fn read_file(file: &Path, request_headers: &HeaderMap) -> std::io::Result<ProtocolResponse> {
+        let content_type = guess_mime_type(file);
+        let mut reader = File::open(file)?;
+        let length = reader.metadata()?.len();
+
+        match RequestedRange::from_headers(request_headers, length) {
+            RequestedRange::Whole => {
+                let mut content = Vec::with_capacity(length as usize);
+                reader.read_to_end(&mut content)?;
+                Ok(content_response(content, &content_type))
+            }
+            RequestedRange::Part { start, end } => {
+                let mut content = Vec::with_capacity((end - start + 1) as usize);
+                reader.seek(SeekFrom::Start(start))?;
+                reader.take(end - start + 1).read_to_end(&mut content)?;
+                Ok(partial_response(
+                    content_response(content, &content_type),
+                    start,
+                    end,
+                    length,
+                ))
+            }
+            RequestedRange::Unsatisfiable => Ok(unsatisfiable_range_response(length)),
+        }
+    }
+
+    fn resolve(&self, uri_path: &str) -> Result<PathBuf, StatusCode> {
+        let relative_path = relative_request_path(uri_path)?;
+
+        let root = self
+            .root
+            .canonicalize()
+            .map_err(|error| status_for_io_error(error.kind()))?;
+
+        let mut file = root
+            .join(relative_path)
+            .canonicalize()
+            .map_err(|error| status_for_io_error(error.kind()))?;
+
+        if file.is_dir() {
+            file = file
+                .join(INDEX_FILE)
+                .canonicalize()
+                .map_err(|error| status_for_io_error(error.kind()))?;
+        }
+
+        // symbolic links may still point outside of the root
+        if !file.starts_with(&root) {
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        if !file.is_file() {
+            return Err(StatusCode::NOT_FOUND);
+        }
+
+        Ok(file)
+    }
+}
+
+fn status_for_io_error(kind: ErrorKind) -> StatusCode {
+    match kind {
+        ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
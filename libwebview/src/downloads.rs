@@ -0,0 +1,119 @@
+use percent_encoding::percent_decode_str;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default)]
+pub struct DownloadDestinations {
+    directory: Option<PathBuf>,
+    destinations: HashMap<String, PathBuf>,
+}
+
+impl DownloadDestinations {
+    pub fn set_directory(&mut self, directory: impl Into<PathBuf>) {
+        self.directory = Some(absolute_path(directory.into()));
+    }
+
+    pub fn set_destination(&mut self, url: impl Into<String>, destination: impl Into<PathBuf>) {
+        self.destinations
+            .insert(url.into(), absolute_path(destination.into()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.directory.is_none() && self.destinations.is_empty()
+    }
+
+    /// Redirects the download to its configured destination and returns the suggested file name
+    pub fn apply(&self, url: &str, destination: &mut PathBuf) -> String {
+        let suggested_filename = suggested_filename(url, destination);
+
+        if let Some(url_destination) = self.destinations.get(url) {
+            *destination = url_destination.clone();
+        } else if let Some(directory) = self.directory.as_ref() {
+            *destination = directory.join(&suggested_filename);
+        }
+
+        suggested_filename
+    }
+}
+
+/// The file name is joined onto the download directory, so it is reduced to a single
+/// component that can not escape it
+fn suggested_filename(url: &str, destination: &Path) -> String {
+    destination
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().to_string())
+        .or_else(|| {
+            url.split(|c| matches!(c, '?' | '#'))
+                .next()
+                .and_then(|path| path.rsplit('/').next())
+                .map(|file_name| {
+                    percent_decode_str(file_name)
+                        .decode_utf8_lossy()
+                        .to_string()
+                })
+        })
+        .and_then(|file_name| single_component(&file_name))
+        .unwrap_or_else(|| "download".to_string())
+}
+
+fn single_component(file_name: &str) -> Option<String> {
+    let file_name = file_name.rsplit(['/', '\\']).next()?;
+    if file_name.is_empty() || file_name == "." || file_name == ".." {
+        return None;
+    }
+    Path::new(file_name)
+        .file_name()
+        .filter(|component| *component == file_name)
+        .map(|_| file_name.to_string())
+}
+
+fn absolute_path(path: PathBuf) -> PathBuf {
+    if path.is_absolute() {
+        return path;
+    }
+    std::env::current_dir()
+        .map(|current_dir| current_dir.join(&path))
+        .unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filename(url: &str) -> String {
+        suggested_filename(url, Path::new(""))
+    }
+
+    #[test]
+    fn file_name_of_the_url() {
+        assert_eq!(
+            filename("https://a.com/files/report.pdf?x=1#y"),
+            "report.pdf"
+        );
+        assert_eq!(
+            filename("https://a.com/files/my%20report.pdf"),
+            "my report.pdf"
+        );
+        assert_eq!(filename("https://a.com/files/"), "download");
+    }
+
+    #[test]
+    fn traversal_stays_in_the_directory() {
+        assert_eq!(filename("https://a.com/x/..%2F..%2F.bashrc"), ".bashrc");
+        assert_eq!(filename("https://a.com/x/..%5C..%5Cevil.exe"), "evil.exe");
+        assert_eq!(filename("https://a.com/x/%2E%2E"), "download");
+        assert_eq!(filename("https://a.com/x/..%2F"), "download");
+    }
+
+    #[test]
+    fn directory_receives_a_single_component() {
+        let mut destinations = DownloadDestinations::default();
+        destinations.set_directory("/downloads");
+        let mut destination = PathBuf::new();
+        destinations.apply("https://a.com/..%2F..%2Fetc%2Fpasswd", &mut destination);
+        assert_eq!(
+            destination,
+            absolute_path("/downloads".into()).join("passwd")
+        );
+    }
+}
@@ -1,11 +1,20 @@
+use anyhow::anyhow;
 use std::any::type_name;
-use std::collections::VecDeque;
 use std::ffi::c_void;
 use std::fmt::{Debug, Formatter};
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 use string_box::StringBox;
 
-use value_box::{ReturnBoxerResult, ValueBox, ValueBoxPointer};
+use crate::events_queue::{EventsOverflowPolicy, EventsQueue, WebViewEvents};
+use crate::navigation_decision::NavigationRequestId;
+use crate::new_window::NewWindowPolicy;
+use crate::protocol::ProtocolResponder;
+use crate::rpc::{RpcCall, RpcCallId};
+use value_box::{ReturnBoxerResult, ValueBox, ValueBoxIntoRaw, ValueBoxPointer};
 use wry::http::Request;
 use wry::PageLoadEvent;
 
@@ -15,77 +24,407 @@ pub struct EventsHandler(Arc<EventsHandlerData>);
 pub type WebViewId = u64;
 
 struct EventsHandlerData {
-    events: Mutex<VecDeque<WebViewEvent>>,
+    events: Mutex<EventsQueue>,
+    untrusted_requests: AtomicUsize,
+    /// one bit per enabled `WebViewEventType`
+    mask: AtomicU64,
+    /// only signal when the queue stops being empty, see `webview_events_handler_rearm`
+    is_edge_triggered: AtomicBool,
+    is_armed: AtomicBool,
     semaphore_index: usize,
     semaphore_signaller: unsafe extern "C" fn(usize),
 }
 
 impl EventsHandler {
-    pub fn enqueue_request(&self, webview_id: WebViewId, request: Request<String>) {
-        self.enqueue_event(WebViewEvent::Request(WebViewRequestEvent {
+    pub fn enqueue_request(&self, webview_id: WebViewId, origin: String, request: Request<String>) {
+        if !self.is_enabled(WebViewEventType::Request) {
+            return;
+        }
+        let rpc_call = RpcCall::parse(request.body());
+        self.enqueue_event(WebViewEventKind::Request(WebViewRequestEvent {
             webview_id,
+            origin,
             request,
+            rpc_call,
         }));
     }
 
+    pub fn drop_untrusted_request(&self) {
+        self.0.untrusted_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn untrusted_requests_count(&self) -> usize {
+        self.0.untrusted_requests.load(Ordering::Relaxed)
+    }
+
     pub fn enqueue_navigation(&self, webview_id: WebViewId, url: String) {
-        self.enqueue_event(WebViewEvent::Navigation(WebViewNavigationEvent {
+        if !self.is_enabled(WebViewEventType::Navigation) {
+            return;
+        }
+        self.enqueue_event(WebViewEventKind::Navigation(WebViewNavigationEvent {
             webview_id,
             url,
         }));
     }
 
+    pub fn enqueue_navigation_blocked(&self, webview_id: WebViewId, url: String) {
+        if !self.is_enabled(WebViewEventType::NavigationBlocked) {
+            return;
+        }
+        self.enqueue_event(WebViewEventKind::NavigationBlocked(
+            WebViewNavigationBlockedEvent { webview_id, url },
+        ));
+    }
+
+    #[allow(dead_code)]
+    pub fn enqueue_navigation_request(
+        &self,
+        webview_id: WebViewId,
+        request_id: NavigationRequestId,
+        url: String,
+    ) {
+        if !self.is_enabled(WebViewEventType::NavigationRequest) {
+            return;
+        }
+        self.enqueue_event(WebViewEventKind::NavigationRequest(
+            WebViewNavigationRequestEvent {
+                webview_id,
+                request_id,
+                url,
+            },
+        ));
+    }
+
+    pub fn enqueue_new_window_requested(
+        &self,
+        webview_id: WebViewId,
+        url: String,
+        features: String,
+        policy: NewWindowPolicy,
+    ) {
+        if !self.is_enabled(WebViewEventType::NewWindowRequested) {
+            return;
+        }
+        self.enqueue_event(WebViewEventKind::NewWindowRequested(
+            WebViewNewWindowRequestedEvent {
+                webview_id,
+                url,
+                features,
+                policy,
+            },
+        ));
+    }
+
+    pub fn enqueue_download_started(
+        &self,
+        webview_id: WebViewId,
+        url: String,
+        suggested_filename: String,
+        destination: PathBuf,
+    ) {
+        if !self.is_enabled(WebViewEventType::DownloadStarted) {
+            return;
+        }
+        self.enqueue_event(WebViewEventKind::DownloadStarted(
+            WebViewDownloadStartedEvent {
+                webview_id,
+                url,
+                suggested_filename,
+                destination,
+            },
+        ));
+    }
+
+    pub fn enqueue_download_completed(
+        &self,
+        webview_id: WebViewId,
+        url: String,
+        destination: Option<PathBuf>,
+        is_success: bool,
+    ) {
+        if !self.is_enabled(WebViewEventType::DownloadCompleted) {
+            return;
+        }
+        self.enqueue_event(WebViewEventKind::DownloadCompleted(
+            WebViewDownloadCompletedEvent {
+                webview_id,
+                url,
+                destination,
+                is_success,
+            },
+        ));
+    }
+
+    pub fn enqueue_title_changed(&self, webview_id: WebViewId, title: String) {
+        if !self.is_enabled(WebViewEventType::TitleChanged) {
+            return;
+        }
+        self.enqueue_event(WebViewEventKind::TitleChanged(WebViewTitleChangedEvent {
+            webview_id,
+            title,
+        }));
+    }
+
+    pub fn enqueue_drag_drop(
+        &self,
+        webview_id: WebViewId,
+        event_type: WebViewEventType,
+        paths: Vec<PathBuf>,
+        position: (i32, i32),
+    ) {
+        if !self.is_enabled(event_type) {
+            return;
+        }
+        let event = WebViewDragDropEvent {
+            webview_id,
+            paths,
+            position,
+        };
+        self.enqueue_event(match event_type {
+            WebViewEventType::DragEnter => WebViewEventKind::DragEnter(event),
+            WebViewEventType::DragOver => WebViewEventKind::DragOver(event),
+            WebViewEventType::Drop => WebViewEventKind::Drop(event),
+            _ => WebViewEventKind::DragLeave(event),
+        });
+    }
+
     pub fn enqueue_page_load(&self, webview_id: WebViewId, page_event: PageLoadEvent, url: String) {
-        self.enqueue_event(WebViewEvent::PageLoad(WebViewPageLoadEvent {
+        if !self.is_enabled(WebViewEventType::PageLoad) {
+            return;
+        }
+        self.enqueue_event(WebViewEventKind::PageLoad(WebViewPageLoadEvent {
             webview_id,
             page_event,
             url,
         }));
     }
 
+    pub fn enqueue_protocol_request(
+        &self,
+        webview_id: WebViewId,
+        request: Request<Vec<u8>>,
+        responder: ProtocolResponder,
+    ) {
+        if !self.is_enabled(WebViewEventType::ProtocolRequest) {
+            return;
+        }
+        self.enqueue_event(WebViewEventKind::ProtocolRequest(
+            WebViewProtocolRequestEvent {
+                webview_id,
+                request,
+                responder,
+            },
+        ));
+    }
+
     #[allow(dead_code)]
     pub fn enqueue_got_focus(&self, webview_id: WebViewId) {
-        self.enqueue_event(WebViewEvent::GotFocus(WebViewGotFocusEvent { webview_id }));
+        if !self.is_enabled(WebViewEventType::GotFocus) {
+            return;
+        }
+        self.enqueue_event(WebViewEventKind::GotFocus(WebViewGotFocusEvent {
+            webview_id,
+        }));
     }
 
     #[allow(dead_code)]
     pub fn enqueue_lost_focus(&self, webview_id: WebViewId) {
-        self.enqueue_event(WebViewEvent::LostFocus(WebViewLostFocusEvent {
+        if !self.is_enabled(WebViewEventType::LostFocus) {
+            return;
+        }
+        self.enqueue_event(WebViewEventKind::LostFocus(WebViewLostFocusEvent {
             webview_id,
         }));
     }
 
-    fn enqueue_event(&self, event: WebViewEvent) {
+    /// Disabled events are neither built nor signalled
+    pub fn is_enabled(&self, event_type: WebViewEventType) -> bool {
+        self.0.mask.load(Ordering::Relaxed) & event_type.mask_bit() != 0
+    }
+
+    pub fn set_mask(&self, mask: u64) {
+        self.0.mask.store(mask, Ordering::Relaxed);
+    }
+
+    pub fn mask(&self) -> u64 {
+        self.0.mask.load(Ordering::Relaxed)
+    }
+
+    fn enqueue_event(&self, kind: WebViewEventKind) {
         let mut lock = self.0.events.lock().unwrap();
-        lock.push_back(event);
+        let event = WebViewEvent::new(kind);
+        let was_empty = lock.is_empty();
+        if lock.push(event) && self.should_signal(was_empty) {
+            self.signal_semaphore();
+        }
+    }
+
+    /// Must be called with the events locked
+    fn should_signal(&self, was_empty: bool) -> bool {
+        if !self.0.is_edge_triggered.load(Ordering::SeqCst) {
+            return true;
+        }
+        was_empty && self.0.is_armed.swap(false, Ordering::SeqCst)
+    }
+
+    fn signal_semaphore(&self) {
         unsafe { (self.0.semaphore_signaller)(self.0.semaphore_index) };
     }
 
+    pub fn set_edge_triggered(&self, is_edge_triggered: bool) {
+        let _lock = self.0.events.lock().unwrap();
+        self.0
+            .is_edge_triggered
+            .store(is_edge_triggered, Ordering::SeqCst);
+        self.0.is_armed.store(true, Ordering::SeqCst);
+    }
+
+    /// Events enqueued after the queue was drained but before re-arming signal right away
+    pub fn rearm(&self) {
+        let lock = self.0.events.lock().unwrap();
+        if lock.is_empty() {
+            self.0.is_armed.store(true, Ordering::SeqCst);
+        } else {
+            self.0.is_armed.store(false, Ordering::SeqCst);
+            self.signal_semaphore();
+        }
+    }
+
     pub fn pop_event(&self) -> Option<WebViewEvent> {
         let mut lock = self.0.events.lock().unwrap();
-        lock.pop_front()
+        lock.pop()
+    }
+
+    pub fn pop_events(&self, max_count: usize) -> Vec<WebViewEvent> {
+        let mut lock = self.0.events.lock().unwrap();
+        lock.pop_many(max_count)
+    }
+
+    pub fn set_capacity(&self, capacity: Option<usize>, overflow_policy: EventsOverflowPolicy) {
+        let mut lock = self.0.events.lock().unwrap();
+        lock.set_capacity(capacity, overflow_policy);
+    }
+
+    pub fn dropped_events_count(&self, event_type: WebViewEventType) -> usize {
+        let lock = self.0.events.lock().unwrap();
+        lock.dropped_count(event_type)
+    }
+}
+
+static NEXT_SEQUENCE_NUMBER: AtomicU64 = AtomicU64::new(1);
+
+/// An event as enqueued. Sequence numbers are shared by all events handlers, so events of
+/// different webviews can be ordered
+#[derive(Debug)]
+pub struct WebViewEvent {
+    sequence_number: u64,
+    /// microseconds since the unix epoch
+    timestamp: u64,
+    kind: WebViewEventKind,
+}
+
+impl WebViewEvent {
+    /// Must be called with the events locked, so that they are queued in sequence
+    fn new(kind: WebViewEventKind) -> Self {
+        Self {
+            sequence_number: NEXT_SEQUENCE_NUMBER.fetch_add(1, Ordering::Relaxed),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_micros() as u64)
+                .unwrap_or(0),
+            kind,
+        }
+    }
+
+    pub fn sequence_number(&self) -> u64 {
+        self.sequence_number
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    pub fn into_boxed_inner(self) -> BoxedInnerEvent {
+        self.kind.into_boxed_inner()
+    }
+}
+
+impl Deref for WebViewEvent {
+    type Target = WebViewEventKind;
+
+    fn deref(&self) -> &Self::Target {
+        &self.kind
     }
 }
 
 #[derive(Debug)]
-pub enum WebViewEvent {
+pub enum WebViewEventKind {
     Request(WebViewRequestEvent),
     Navigation(WebViewNavigationEvent),
     PageLoad(WebViewPageLoadEvent),
     GotFocus(WebViewGotFocusEvent),
     LostFocus(WebViewLostFocusEvent),
+    ProtocolRequest(WebViewProtocolRequestEvent),
+    NavigationBlocked(WebViewNavigationBlockedEvent),
+    NavigationRequest(WebViewNavigationRequestEvent),
+    NewWindowRequested(WebViewNewWindowRequestedEvent),
+    DownloadStarted(WebViewDownloadStartedEvent),
+    DownloadCompleted(WebViewDownloadCompletedEvent),
+    TitleChanged(WebViewTitleChangedEvent),
+    DragEnter(WebViewDragDropEvent),
+    DragOver(WebViewDragDropEvent),
+    Drop(WebViewDragDropEvent),
+    DragLeave(WebViewDragDropEvent),
+}
+
+/// An event of a specific type in a value box, released on drop unless given to the host
+#[derive(Debug)]
+pub struct BoxedInnerEvent {
+    pointer: *mut c_void,
+    release: fn(*mut c_void),
+}
+
+impl BoxedInnerEvent {
+    fn new<T: 'static>(event: T) -> Self {
+        Self {
+            pointer: ValueBox::new(event).into_raw() as *mut c_void,
+            release: |pointer| (pointer as *mut ValueBox<T>).release(),
+        }
+    }
+
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.pointer
+    }
+
+    /// The host becomes responsible for releasing the event
+    pub fn into_raw(self) -> *mut c_void {
+        let pointer = self.pointer;
+        std::mem::forget(self);
+        pointer
+    }
+}
+
+impl Drop for BoxedInnerEvent {
+    fn drop(&mut self) {
+        (self.release)(self.pointer)
+    }
 }
 
 pub struct WebViewRequestEvent {
     webview_id: u64,
+    /// the origin of the posting frame, which may differ from the one of the request uri
+    origin: String,
     request: Request<String>,
+    rpc_call: Option<RpcCall>,
 }
 
 impl Debug for WebViewRequestEvent {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct(type_name::<Self>())
             .field("webview_id", &self.webview_id)
+            .field("origin", &self.origin)
             .field("request", self.request.body())
+            .field("rpc_call", &self.rpc_call)
             .finish()
     }
 }
@@ -104,6 +443,122 @@ impl Debug for WebViewNavigationEvent {
     }
 }
 
+pub struct WebViewNavigationBlockedEvent {
+    webview_id: u64,
+    url: String,
+}
+
+impl Debug for WebViewNavigationBlockedEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(type_name::<Self>())
+            .field("webview_id", &self.webview_id)
+            .field("url", &self.url)
+            .finish()
+    }
+}
+
+pub struct WebViewNavigationRequestEvent {
+    webview_id: u64,
+    request_id: NavigationRequestId,
+    url: String,
+}
+
+impl Debug for WebViewNavigationRequestEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(type_name::<Self>())
+            .field("webview_id", &self.webview_id)
+            .field("request_id", &self.request_id)
+            .field("url", &self.url)
+            .finish()
+    }
+}
+
+pub struct WebViewNewWindowRequestedEvent {
+    webview_id: u64,
+    url: String,
+    /// the features passed to `window.open`, empty for links
+    features: String,
+    policy: NewWindowPolicy,
+}
+
+impl Debug for WebViewNewWindowRequestedEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(type_name::<Self>())
+            .field("webview_id", &self.webview_id)
+            .field("url", &self.url)
+            .field("features", &self.features)
+            .field("policy", &self.policy)
+            .finish()
+    }
+}
+
+pub struct WebViewDownloadStartedEvent {
+    webview_id: u64,
+    url: String,
+    suggested_filename: String,
+    destination: PathBuf,
+}
+
+impl Debug for WebViewDownloadStartedEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(type_name::<Self>())
+            .field("webview_id", &self.webview_id)
+            .field("url", &self.url)
+            .field("suggested_filename", &self.suggested_filename)
+            .field("destination", &self.destination)
+            .finish()
+    }
+}
+
+pub struct WebViewDownloadCompletedEvent {
+    webview_id: u64,
+    url: String,
+    destination: Option<PathBuf>,
+    is_success: bool,
+}
+
+impl Debug for WebViewDownloadCompletedEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(type_name::<Self>())
+            .field("webview_id", &self.webview_id)
+            .field("url", &self.url)
+            .field("destination", &self.destination)
+            .field("is_success", &self.is_success)
+            .finish()
+    }
+}
+
+pub struct WebViewTitleChangedEvent {
+    webview_id: u64,
+    title: String,
+}
+
+impl Debug for WebViewTitleChangedEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(type_name::<Self>())
+            .field("webview_id", &self.webview_id)
+            .field("title", &self.title)
+            .finish()
+    }
+}
+
+/// Shared by all drag and drop events, the position is relative to the webview
+pub struct WebViewDragDropEvent {
+    webview_id: u64,
+    paths: Vec<PathBuf>,
+    position: (i32, i32),
+}
+
+impl Debug for WebViewDragDropEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(type_name::<Self>())
+            .field("webview_id", &self.webview_id)
+            .field("paths", &self.paths)
+            .field("position", &self.position)
+            .finish()
+    }
+}
+
 pub struct WebViewPageLoadEvent {
     webview_id: u64,
     page_event: PageLoadEvent,
@@ -126,6 +581,24 @@ impl Debug for WebViewPageLoadEvent {
     }
 }
 
+pub struct WebViewProtocolRequestEvent {
+    webview_id: u64,
+    request: Request<Vec<u8>>,
+    responder: ProtocolResponder,
+}
+
+impl Debug for WebViewProtocolRequestEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(type_name::<Self>())
+            .field("webview_id", &self.webview_id)
+            .field("method", self.request.method())
+            .field("uri", self.request.uri())
+            .field("headers", self.request.headers())
+            .field("body_length", &self.request.body().len())
+            .finish()
+    }
+}
+
 #[derive(Debug)]
 pub struct WebViewGotFocusEvent {
     webview_id: u64,
@@ -136,7 +609,65 @@ pub struct WebViewLostFocusEvent {
     webview_id: u64,
 }
 
-impl WebViewEvent {
+impl WebViewEventKind {
+    /// Boxes the event of the specific type, as expected by its accessors
+    pub fn into_boxed_inner(self) -> BoxedInnerEvent {
+        match self {
+            Self::Request(event) => BoxedInnerEvent::new(event),
+            Self::Navigation(event) => BoxedInnerEvent::new(event),
+            Self::PageLoad(event) => BoxedInnerEvent::new(event),
+            Self::GotFocus(event) => BoxedInnerEvent::new(event),
+            Self::LostFocus(event) => BoxedInnerEvent::new(event),
+            Self::ProtocolRequest(event) => BoxedInnerEvent::new(event),
+            Self::NavigationBlocked(event) => BoxedInnerEvent::new(event),
+            Self::NavigationRequest(event) => BoxedInnerEvent::new(event),
+            Self::NewWindowRequested(event) => BoxedInnerEvent::new(event),
+            Self::DownloadStarted(event) => BoxedInnerEvent::new(event),
+            Self::DownloadCompleted(event) => BoxedInnerEvent::new(event),
+            Self::TitleChanged(event) => BoxedInnerEvent::new(event),
+            Self::DragEnter(event) => BoxedInnerEvent::new(event),
+            Self::DragOver(event) => BoxedInnerEvent::new(event),
+            Self::Drop(event) => BoxedInnerEvent::new(event),
+            Self::DragLeave(event) => BoxedInnerEvent::new(event),
+        }
+    }
+
+    pub fn webview_id(&self) -> WebViewId {
+        match self {
+            Self::Request(event) => event.webview_id,
+            Self::Navigation(event) => event.webview_id,
+            Self::PageLoad(event) => event.webview_id,
+            Self::GotFocus(event) => event.webview_id,
+            Self::LostFocus(event) => event.webview_id,
+            Self::ProtocolRequest(event) => event.webview_id,
+            Self::NavigationBlocked(event) => event.webview_id,
+            Self::NavigationRequest(event) => event.webview_id,
+            Self::NewWindowRequested(event) => event.webview_id,
+            Self::DownloadStarted(event) => event.webview_id,
+            Self::DownloadCompleted(event) => event.webview_id,
+            Self::TitleChanged(event) => event.webview_id,
+            Self::DragEnter(event) => event.webview_id,
+            Self::DragOver(event) => event.webview_id,
+            Self::Drop(event) => event.webview_id,
+            Self::DragLeave(event) => event.webview_id,
+        }
+    }
+
+    /// Only events describing the latest state of a webview can replace each other
+    pub fn is_coalesced_by(&self, event: &WebViewEventKind) -> bool {
+        let event_type = self.get_type();
+        matches!(
+            event_type,
+            WebViewEventType::Navigation
+                | WebViewEventType::PageLoad
+                | WebViewEventType::GotFocus
+                | WebViewEventType::LostFocus
+                | WebViewEventType::TitleChanged
+                | WebViewEventType::DragOver
+        ) && event_type == event.get_type()
+            && self.webview_id() == event.webview_id()
+    }
+
     pub fn get_type(&self) -> WebViewEventType {
         match self {
             Self::Request(_) => WebViewEventType::Request,
@@ -144,11 +675,22 @@ impl WebViewEvent {
             Self::PageLoad(_) => WebViewEventType::PageLoad,
             Self::GotFocus(_) => WebViewEventType::GotFocus,
             Self::LostFocus(_) => WebViewEventType::LostFocus,
+            Self::ProtocolRequest(_) => WebViewEventType::ProtocolRequest,
+            Self::NavigationBlocked(_) => WebViewEventType::NavigationBlocked,
+            Self::NavigationRequest(_) => WebViewEventType::NavigationRequest,
+            Self::NewWindowRequested(_) => WebViewEventType::NewWindowRequested,
+            Self::DownloadStarted(_) => WebViewEventType::DownloadStarted,
+            Self::DownloadCompleted(_) => WebViewEventType::DownloadCompleted,
+            Self::TitleChanged(_) => WebViewEventType::TitleChanged,
+            Self::DragEnter(_) => WebViewEventType::DragEnter,
+            Self::DragOver(_) => WebViewEventType::DragOver,
+            Self::Drop(_) => WebViewEventType::Drop,
+            Self::DragLeave(_) => WebViewEventType::DragLeave,
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
 pub enum WebViewEventType {
     Unknown,
@@ -157,6 +699,50 @@ pub enum WebViewEventType {
     PageLoad,
     GotFocus,
     LostFocus,
+    ProtocolRequest,
+    NavigationBlocked,
+    NavigationRequest,
+    NewWindowRequested,
+    DownloadStarted,
+    DownloadCompleted,
+    TitleChanged,
+    DragEnter,
+    DragOver,
+    Drop,
+    DragLeave,
+}
+
+impl TryFrom<u8> for WebViewEventType {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Unknown),
+            1 => Ok(Self::Request),
+            2 => Ok(Self::Navigation),
+            3 => Ok(Self::PageLoad),
+            4 => Ok(Self::GotFocus),
+            5 => Ok(Self::LostFocus),
+            6 => Ok(Self::ProtocolRequest),
+            7 => Ok(Self::NavigationBlocked),
+            8 => Ok(Self::NavigationRequest),
+            9 => Ok(Self::NewWindowRequested),
+            10 => Ok(Self::DownloadStarted),
+            11 => Ok(Self::DownloadCompleted),
+            12 => Ok(Self::TitleChanged),
+            13 => Ok(Self::DragEnter),
+            14 => Ok(Self::DragOver),
+            15 => Ok(Self::Drop),
+            16 => Ok(Self::DragLeave),
+            _ => Err(format!("Unknown event type {}", value)),
+        }
+    }
+}
+
+impl WebViewEventType {
+    pub fn mask_bit(self) -> u64 {
+        1 << (self as u8)
+    }
 }
 
 #[no_mangle]
@@ -166,6 +752,10 @@ pub extern "C" fn webview_events_handler_new(
 ) -> *mut ValueBox<EventsHandler> {
     ValueBox::new(EventsHandler(Arc::new(EventsHandlerData {
         events: Default::default(),
+        untrusted_requests: Default::default(),
+        mask: AtomicU64::new(u64::MAX),
+        is_edge_triggered: AtomicBool::new(false),
+        is_armed: AtomicBool::new(true),
         semaphore_index,
         semaphore_signaller,
     })))
@@ -186,6 +776,93 @@ pub extern "C" fn webview_events_handler_pop_event(
         .or_log(std::ptr::null_mut())
 }
 
+/// Pops at most `max_count` events at once, the batch is empty if there are no events
+#[no_mangle]
+pub extern "C" fn webview_events_handler_pop_events(
+    handler: *mut ValueBox<EventsHandler>,
+    max_count: usize,
+) -> *mut ValueBox<WebViewEvents> {
+    handler
+        .with_ref_ok(|handler| ValueBox::new(WebViewEvents::new(handler.pop_events(max_count))))
+        .into_raw()
+}
+
+#[no_mangle]
+pub extern "C" fn webview_events_handler_get_untrusted_requests_count(
+    handler: *mut ValueBox<EventsHandler>,
+) -> usize {
+    handler
+        .with_ref_ok(|handler| handler.untrusted_requests_count())
+        .or_log(0)
+}
+
+/// Bit `n` of the mask enables events of the `WebViewEventType` with value `n`, all are enabled
+/// by default. Masked events can not be answered: protocol requests are answered with 500,
+/// `window.host.call` promises stay pending and navigations allowed by the policy proceed
+#[no_mangle]
+pub extern "C" fn webview_events_handler_set_mask(
+    handler: *mut ValueBox<EventsHandler>,
+    mask: u64,
+) {
+    handler.with_ref_ok(|handler| handler.set_mask(mask)).log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_events_handler_get_mask(handler: *mut ValueBox<EventsHandler>) -> u64 {
+    handler.with_ref_ok(|handler| handler.mask()).or_log(0)
+}
+
+/// In edge triggered mode the semaphore is only signalled when the queue stops being empty.
+/// The host must then pop all events and call `webview_events_handler_rearm`
+#[no_mangle]
+pub extern "C" fn webview_events_handler_set_edge_triggered(
+    handler: *mut ValueBox<EventsHandler>,
+    is_edge_triggered: bool,
+) {
+    handler
+        .with_ref_ok(|handler| handler.set_edge_triggered(is_edge_triggered))
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_events_handler_rearm(handler: *mut ValueBox<EventsHandler>) {
+    handler.with_ref_ok(|handler| handler.rearm()).log();
+}
+
+/// A capacity of 0 leaves the queue unbounded
+#[no_mangle]
+pub extern "C" fn webview_events_handler_set_capacity(
+    handler: *mut ValueBox<EventsHandler>,
+    capacity: usize,
+    overflow_policy: u8,
+) -> bool {
+    handler
+        .with_ref(|handler| {
+            let overflow_policy =
+                EventsOverflowPolicy::try_from(overflow_policy).map_err(|error| anyhow!(error))?;
+            handler.set_capacity(
+                Some(capacity).filter(|capacity| *capacity > 0),
+                overflow_policy,
+            );
+            Ok(true)
+        })
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_events_handler_get_dropped_events_count(
+    handler: *mut ValueBox<EventsHandler>,
+    event_type: u8,
+) -> usize {
+    handler
+        .with_ref(|handler| {
+            let event_type =
+                WebViewEventType::try_from(event_type).map_err(|error| anyhow!(error))?;
+            Ok(handler.dropped_events_count(event_type))
+        })
+        .or_log(0)
+}
+
 #[no_mangle]
 pub extern "C" fn webview_events_handler_release(handler: *mut ValueBox<EventsHandler>) {
     handler.release();
@@ -198,17 +875,22 @@ pub extern "C" fn webview_event_get_type(event: *mut ValueBox<WebViewEvent>) ->
         .or_log(WebViewEventType::Unknown)
 }
 
+#[no_mangle]
+pub extern "C" fn webview_event_get_sequence_number(event: *mut ValueBox<WebViewEvent>) -> u64 {
+    event.with_ref_ok(|event| event.sequence_number).or_log(0)
+}
+
+/// Microseconds since the unix epoch at which the event was enqueued
+#[no_mangle]
+pub extern "C" fn webview_event_get_timestamp(event: *mut ValueBox<WebViewEvent>) -> u64 {
+    event.with_ref_ok(|event| event.timestamp).or_log(0)
+}
+
 #[no_mangle]
 pub extern "C" fn webview_event_into_inner(event: *mut ValueBox<WebViewEvent>) -> *mut c_void {
     event
         .take_value()
-        .map(|value| match value {
-            WebViewEvent::Request(event) => ValueBox::new(event).into_raw() as *mut c_void,
-            WebViewEvent::Navigation(event) => ValueBox::new(event).into_raw() as *mut c_void,
-            WebViewEvent::PageLoad(event) => ValueBox::new(event).into_raw() as *mut c_void,
-            WebViewEvent::GotFocus(event) => ValueBox::new(event).into_raw() as *mut c_void,
-            WebViewEvent::LostFocus(event) => ValueBox::new(event).into_raw() as *mut c_void,
-        })
+        .map(|value| value.into_boxed_inner().into_raw())
         .or_log(std::ptr::null_mut())
 }
 
@@ -230,7 +912,176 @@ pub extern "C" fn webview_navigation_event_get_id(
 }
 
 #[no_mangle]
-pub extern "C" fn webview_request_event_get_content(
+pub extern "C" fn webview_navigation_blocked_event_get_url(
+    event: *mut ValueBox<WebViewNavigationBlockedEvent>,
+    url: *mut ValueBox<StringBox>,
+) {
+    event
+        .with_ref(|event| url.with_mut_ok(|url| url.set_string(event.url.clone())))
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_navigation_blocked_event_get_id(
+    event: *mut ValueBox<WebViewNavigationBlockedEvent>,
+) -> WebViewId {
+    event.with_ref_ok(|event| event.webview_id).or_log(0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_navigation_request_event_get_url(
+    event: *mut ValueBox<WebViewNavigationRequestEvent>,
+    url: *mut ValueBox<StringBox>,
+) {
+    event
+        .with_ref(|event| url.with_mut_ok(|url| url.set_string(event.url.clone())))
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_navigation_request_event_get_id(
+    event: *mut ValueBox<WebViewNavigationRequestEvent>,
+) -> WebViewId {
+    event.with_ref_ok(|event| event.webview_id).or_log(0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_navigation_request_event_get_request_id(
+    event: *mut ValueBox<WebViewNavigationRequestEvent>,
+) -> NavigationRequestId {
+    event.with_ref_ok(|event| event.request_id).or_log(0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_new_window_requested_event_get_url(
+    event: *mut ValueBox<WebViewNewWindowRequestedEvent>,
+    url: *mut ValueBox<StringBox>,
+) {
+    event
+        .with_ref(|event| url.with_mut_ok(|url| url.set_string(event.url.clone())))
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_new_window_requested_event_get_features(
+    event: *mut ValueBox<WebViewNewWindowRequestedEvent>,
+    features: *mut ValueBox<StringBox>,
+) {
+    event
+        .with_ref(|event| {
+            features.with_mut_ok(|features| features.set_string(event.features.clone()))
+        })
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_new_window_requested_event_get_id(
+    event: *mut ValueBox<WebViewNewWindowRequestedEvent>,
+) -> WebViewId {
+    event.with_ref_ok(|event| event.webview_id).or_log(0)
+}
+
+/// The policy that was actually applied, the host is expected to act on `DecidedByHost`
+#[no_mangle]
+pub extern "C" fn webview_new_window_requested_event_get_policy(
+    event: *mut ValueBox<WebViewNewWindowRequestedEvent>,
+) -> NewWindowPolicy {
+    event
+        .with_ref_ok(|event| event.policy)
+        .or_log(NewWindowPolicy::Deny)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_download_started_event_get_id(
+    event: *mut ValueBox<WebViewDownloadStartedEvent>,
+) -> WebViewId {
+    event.with_ref_ok(|event| event.webview_id).or_log(0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_download_started_event_get_url(
+    event: *mut ValueBox<WebViewDownloadStartedEvent>,
+    url: *mut ValueBox<StringBox>,
+) {
+    event
+        .with_ref(|event| url.with_mut_ok(|url| url.set_string(event.url.clone())))
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_download_started_event_get_suggested_filename(
+    event: *mut ValueBox<WebViewDownloadStartedEvent>,
+    filename: *mut ValueBox<StringBox>,
+) {
+    event
+        .with_ref(|event| {
+            filename.with_mut_ok(|filename| filename.set_string(event.suggested_filename.clone()))
+        })
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_download_started_event_get_destination(
+    event: *mut ValueBox<WebViewDownloadStartedEvent>,
+    destination: *mut ValueBox<StringBox>,
+) {
+    event
+        .with_ref(|event| {
+            destination.with_mut_ok(|destination| {
+                destination.set_string(event.destination.to_string_lossy().to_string())
+            })
+        })
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_download_completed_event_get_id(
+    event: *mut ValueBox<WebViewDownloadCompletedEvent>,
+) -> WebViewId {
+    event.with_ref_ok(|event| event.webview_id).or_log(0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_download_completed_event_get_url(
+    event: *mut ValueBox<WebViewDownloadCompletedEvent>,
+    url: *mut ValueBox<StringBox>,
+) {
+    event
+        .with_ref(|event| url.with_mut_ok(|url| url.set_string(event.url.clone())))
+        .log();
+}
+
+/// Returns false when the platform did not report where the file was saved
+#[no_mangle]
+pub extern "C" fn webview_download_completed_event_get_destination(
+    event: *mut ValueBox<WebViewDownloadCompletedEvent>,
+    destination: *mut ValueBox<StringBox>,
+) -> bool {
+    event
+        .with_ref(|event| {
+            destination.with_mut_ok(|destination| match event.destination.as_ref() {
+                Some(path) => {
+                    destination.set_string(path.to_string_lossy().to_string());
+                    true
+                }
+                None => {
+                    destination.set_string(String::new());
+                    false
+                }
+            })
+        })
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_download_completed_event_is_success(
+    event: *mut ValueBox<WebViewDownloadCompletedEvent>,
+) -> bool {
+    event.with_ref_ok(|event| event.is_success).or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_request_event_get_content(
     event: *mut ValueBox<WebViewRequestEvent>,
     content: *mut ValueBox<StringBox>,
 ) {
@@ -241,6 +1092,124 @@ pub extern "C" fn webview_request_event_get_content(
         .log();
 }
 
+#[no_mangle]
+pub extern "C" fn webview_request_event_get_uri(
+    event: *mut ValueBox<WebViewRequestEvent>,
+    uri: *mut ValueBox<StringBox>,
+) {
+    event
+        .with_ref(|event| uri.with_mut_ok(|uri| uri.set_string(event.request.uri().to_string())))
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_request_event_get_origin(
+    event: *mut ValueBox<WebViewRequestEvent>,
+    origin: *mut ValueBox<StringBox>,
+) {
+    event
+        .with_ref(|event| origin.with_mut_ok(|origin| origin.set_string(event.origin.clone())))
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_request_event_get_headers_count(
+    event: *mut ValueBox<WebViewRequestEvent>,
+) -> usize {
+    event
+        .with_ref_ok(|event| event.request.headers().len())
+        .or_log(0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_request_event_get_header_at(
+    event: *mut ValueBox<WebViewRequestEvent>,
+    index: usize,
+    name: *mut ValueBox<StringBox>,
+    value: *mut ValueBox<StringBox>,
+) -> bool {
+    event
+        .with_ref(|event| {
+            name.with_mut(|name| {
+                value.with_mut_ok(|value| {
+                    event
+                        .request
+                        .headers()
+                        .iter()
+                        .nth(index)
+                        .map(|(header_name, header_value)| {
+                            name.set_string(header_name.to_string());
+                            value.set_string(
+                                String::from_utf8_lossy(header_value.as_bytes()).to_string(),
+                            );
+                            true
+                        })
+                        .unwrap_or(false)
+                })
+            })
+        })
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_request_event_is_rpc_call(
+    event: *mut ValueBox<WebViewRequestEvent>,
+) -> bool {
+    event
+        .with_ref_ok(|event| event.rpc_call.is_some())
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_request_event_get_rpc_call_id(
+    event: *mut ValueBox<WebViewRequestEvent>,
+) -> RpcCallId {
+    event
+        .with_ref_ok(|event| event.rpc_call.as_ref().map_or(0, |call| call.id))
+        .or_log(0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_request_event_get_rpc_method(
+    event: *mut ValueBox<WebViewRequestEvent>,
+    method: *mut ValueBox<StringBox>,
+) {
+    event
+        .with_ref(|event| {
+            method.with_mut_ok(|method| {
+                method.set_string(
+                    event
+                        .rpc_call
+                        .as_ref()
+                        .map(|call| call.method.clone())
+                        .unwrap_or_default(),
+                )
+            })
+        })
+        .log();
+}
+
+/// The json encoded params of the call, `null` when none were given
+#[no_mangle]
+pub extern "C" fn webview_request_event_get_rpc_params(
+    event: *mut ValueBox<WebViewRequestEvent>,
+    params: *mut ValueBox<StringBox>,
+) {
+    event
+        .with_ref(|event| {
+            params.with_mut_ok(|params| {
+                params.set_string(
+                    event
+                        .rpc_call
+                        .as_ref()
+                        .map(|call| call.params.clone())
+                        .unwrap_or_default(),
+                )
+            })
+        })
+        .log();
+}
+
 #[no_mangle]
 pub extern "C" fn webview_request_event_get_id(
     event: *mut ValueBox<WebViewRequestEvent>,
@@ -277,6 +1246,69 @@ pub extern "C" fn webview_page_load_event_is_started(
         .or_log(0)
 }
 
+#[no_mangle]
+pub extern "C" fn webview_title_changed_event_get_title(
+    event: *mut ValueBox<WebViewTitleChangedEvent>,
+    title: *mut ValueBox<StringBox>,
+) {
+    event
+        .with_ref(|event| title.with_mut_ok(|title| title.set_string(event.title.clone())))
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_title_changed_event_get_id(
+    event: *mut ValueBox<WebViewTitleChangedEvent>,
+) -> WebViewId {
+    event.with_ref_ok(|event| event.webview_id).or_log(0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_drag_drop_event_get_id(
+    event: *mut ValueBox<WebViewDragDropEvent>,
+) -> WebViewId {
+    event.with_ref_ok(|event| event.webview_id).or_log(0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_drag_drop_event_get_paths_count(
+    event: *mut ValueBox<WebViewDragDropEvent>,
+) -> usize {
+    event.with_ref_ok(|event| event.paths.len()).or_log(0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_drag_drop_event_get_path_at(
+    event: *mut ValueBox<WebViewDragDropEvent>,
+    index: usize,
+    path: *mut ValueBox<StringBox>,
+) -> bool {
+    event
+        .with_ref(|event| {
+            path.with_mut_ok(|path| {
+                event
+                    .paths
+                    .get(index)
+                    .map(|dragged_path| {
+                        path.set_string(dragged_path.to_string_lossy().to_string());
+                        true
+                    })
+                    .unwrap_or(false)
+            })
+        })
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_drag_drop_event_get_x(event: *mut ValueBox<WebViewDragDropEvent>) -> i32 {
+    event.with_ref_ok(|event| event.position.0).or_log(0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_drag_drop_event_get_y(event: *mut ValueBox<WebViewDragDropEvent>) -> i32 {
+    event.with_ref_ok(|event| event.position.1).or_log(0)
+}
+
 #[no_mangle]
 pub extern "C" fn webview_got_focus_event_get_id(
     event: *mut ValueBox<WebViewGotFocusEvent>,
@@ -291,6 +1323,111 @@ pub extern "C" fn webview_lost_focus_event_get_id(
     event.with_ref_ok(|event| event.webview_id).or_log(0)
 }
 
+#[no_mangle]
+pub extern "C" fn webview_protocol_request_event_get_id(
+    event: *mut ValueBox<WebViewProtocolRequestEvent>,
+) -> WebViewId {
+    event.with_ref_ok(|event| event.webview_id).or_log(0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_protocol_request_event_get_method(
+    event: *mut ValueBox<WebViewProtocolRequestEvent>,
+    method: *mut ValueBox<StringBox>,
+) {
+    event
+        .with_ref(|event| {
+            method.with_mut_ok(|method| method.set_string(event.request.method().to_string()))
+        })
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_protocol_request_event_get_uri(
+    event: *mut ValueBox<WebViewProtocolRequestEvent>,
+    uri: *mut ValueBox<StringBox>,
+) {
+    event
+        .with_ref(|event| uri.with_mut_ok(|uri| uri.set_string(event.request.uri().to_string())))
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_protocol_request_event_get_headers_count(
+    event: *mut ValueBox<WebViewProtocolRequestEvent>,
+) -> usize {
+    event
+        .with_ref_ok(|event| event.request.headers().len())
+        .or_log(0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_protocol_request_event_get_header_at(
+    event: *mut ValueBox<WebViewProtocolRequestEvent>,
+    index: usize,
+    name: *mut ValueBox<StringBox>,
+    value: *mut ValueBox<StringBox>,
+) -> bool {
+    event
+        .with_ref(|event| {
+            name.with_mut(|name| {
+                value.with_mut_ok(|value| {
+                    event
+                        .request
+                        .headers()
+                        .iter()
+                        .nth(index)
+                        .map(|(header_name, header_value)| {
+                            name.set_string(header_name.to_string());
+                            value.set_string(
+                                String::from_utf8_lossy(header_value.as_bytes()).to_string(),
+                            );
+                            true
+                        })
+                        .unwrap_or(false)
+                })
+            })
+        })
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_protocol_request_event_get_body_length(
+    event: *mut ValueBox<WebViewProtocolRequestEvent>,
+) -> usize {
+    event
+        .with_ref_ok(|event| event.request.body().len())
+        .or_log(0)
+}
+
+/// Copies at most `length` bytes of the request body into the buffer and returns how many were copied.
+#[no_mangle]
+pub extern "C" fn webview_protocol_request_event_copy_body(
+    event: *mut ValueBox<WebViewProtocolRequestEvent>,
+    buffer: *mut u8,
+    length: usize,
+) -> usize {
+    event
+        .with_ref_ok(|event| {
+            let body = event.request.body();
+            let count = body.len().min(length);
+            if !buffer.is_null() && count > 0 {
+                unsafe { std::ptr::copy_nonoverlapping(body.as_ptr(), buffer, count) };
+            }
+            count
+        })
+        .or_log(0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_protocol_request_event_get_responder(
+    event: *mut ValueBox<WebViewProtocolRequestEvent>,
+) -> *mut ValueBox<ProtocolResponder> {
+    event
+        .with_ref_ok(|event| ValueBox::new(event.responder.clone()).into_raw())
+        .or_log(std::ptr::null_mut())
+}
+
 #[no_mangle]
 pub extern "C" fn webview_event_release(event: *mut ValueBox<WebViewEvent>) {
     event.release();
@@ -301,6 +1438,41 @@ pub extern "C" fn webview_navigation_event_release(event: *mut ValueBox<WebViewN
     event.release();
 }
 
+#[no_mangle]
+pub extern "C" fn webview_navigation_blocked_event_release(
+    event: *mut ValueBox<WebViewNavigationBlockedEvent>,
+) {
+    event.release();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_navigation_request_event_release(
+    event: *mut ValueBox<WebViewNavigationRequestEvent>,
+) {
+    event.release();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_new_window_requested_event_release(
+    event: *mut ValueBox<WebViewNewWindowRequestedEvent>,
+) {
+    event.release();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_download_started_event_release(
+    event: *mut ValueBox<WebViewDownloadStartedEvent>,
+) {
+    event.release();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_download_completed_event_release(
+    event: *mut ValueBox<WebViewDownloadCompletedEvent>,
+) {
+    event.release();
+}
+
 #[no_mangle]
 pub extern "C" fn webview_page_load_event_release(event: *mut ValueBox<WebViewPageLoadEvent>) {
     event.release();
@@ -311,6 +1483,18 @@ pub extern "C" fn webview_request_event_release(event: *mut ValueBox<WebViewRequ
     event.release();
 }
 
+#[no_mangle]
+pub extern "C" fn webview_title_changed_event_release(
+    event: *mut ValueBox<WebViewTitleChangedEvent>,
+) {
+    event.release();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_drag_drop_event_release(event: *mut ValueBox<WebViewDragDropEvent>) {
+    event.release();
+}
+
 #[no_mangle]
 pub extern "C" fn webview_got_focus_event_release(event: *mut ValueBox<WebViewGotFocusEvent>) {
     event.release();
@@ -320,3 +1504,10 @@ pub extern "C" fn webview_got_focus_event_release(event: *mut ValueBox<WebViewGo
 pub extern "C" fn webview_lost_focus_event_release(event: *mut ValueBox<WebViewLostFocusEvent>) {
     event.release();
 }
+
+#[no_mangle]
+pub extern "C" fn webview_protocol_request_event_release(
+    event: *mut ValueBox<WebViewProtocolRequestEvent>,
+) {
+    event.release();
+}
@@ -0,0 +1,279 @@
+use crate::events_handler::{BoxedInnerEvent, WebViewEvent, WebViewEventType};
+use anyhow::anyhow;
+use std::collections::{HashMap, VecDeque};
+use std::ffi::c_void;
+use value_box::{ReturnBoxerResult, ValueBox, ValueBoxIntoRaw, ValueBoxPointer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum EventsOverflowPolicy {
+    #[default]
+    DropOldest,
+    DropNewest,
+    /// Replaces a queued event of the same type and webview by the new one.
+    /// Events that can not be coalesced are dropped as the newest
+    Coalesce,
+}
+
+impl TryFrom<u8> for EventsOverflowPolicy {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::DropOldest),
+            1 => Ok(Self::DropNewest),
+            2 => Ok(Self::Coalesce),
+            _ => Err(format!("Unknown events overflow policy {}", value)),
+        }
+    }
+}
+
+/// Queued events waiting for the host, optionally bounded by a capacity.
+/// Navigation requests are never dropped, their decision would otherwise stay pending
+#[derive(Debug, Default)]
+pub struct EventsQueue {
+    events: VecDeque<WebViewEvent>,
+    capacity: Option<usize>,
+    overflow_policy: EventsOverflowPolicy,
+    dropped: HashMap<WebViewEventType, usize>,
+}
+
+impl EventsQueue {
+    /// Events over the new capacity are dropped, the oldest first
+    pub fn set_capacity(&mut self, capacity: Option<usize>, overflow_policy: EventsOverflowPolicy) {
+        self.capacity = capacity;
+        self.overflow_policy = overflow_policy;
+        if let Some(capacity) = capacity {
+            while self.events.len() > capacity && self.drop_oldest() {}
+        }
+    }
+
+    /// Returns false if the event was dropped
+    pub fn push(&mut self, event: WebViewEvent) -> bool {
+        let is_full = self
+            .capacity
+            .map(|capacity| self.events.len() >= capacity)
+            .unwrap_or(false);
+        if !is_full || !is_droppable(&event) {
+            self.events.push_back(event);
+            return true;
+        }
+
+        match self.overflow_policy {
+            EventsOverflowPolicy::DropOldest if self.drop_oldest() => {
+                self.events.push_back(event);
+                true
+            }
+            EventsOverflowPolicy::Coalesce => {
+                let coalesced = self
+                    .events
+                    .iter()
+                    .position(|queued| queued.is_coalesced_by(&event));
+                match coalesced {
+                    Some(index) => {
+                        self.events.remove(index);
+                        self.count_dropped(event.get_type());
+                        self.events.push_back(event);
+                        true
+                    }
+                    None => {
+                        self.count_dropped(event.get_type());
+                        false
+                    }
+                }
+            }
+            _ => {
+                self.count_dropped(event.get_type());
+                false
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn pop(&mut self) -> Option<WebViewEvent> {
+        self.events.pop_front()
+    }
+
+    pub fn pop_many(&mut self, max_count: usize) -> Vec<WebViewEvent> {
+        let count = max_count.min(self.events.len());
+        self.events.drain(..count).collect()
+    }
+
+    pub fn dropped_count(&self, event_type: WebViewEventType) -> usize {
+        self.dropped.get(&event_type).copied().unwrap_or(0)
+    }
+
+    /// Returns false if no queued event can be dropped
+    fn drop_oldest(&mut self) -> bool {
+        let oldest = self.events.iter().position(is_droppable);
+        match oldest.and_then(|index| self.events.remove(index)) {
+            Some(event) => {
+                self.count_dropped(event.get_type());
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn count_dropped(&mut self, event_type: WebViewEventType) {
+        *self.dropped.entry(event_type).or_default() += 1;
+    }
+}
+
+fn is_droppable(event: &WebViewEvent) -> bool {
+    event.get_type() != WebViewEventType::NavigationRequest
+}
+
+/// Events popped together. Each of them can be taken out once, or lent to the host as its
+/// inner event, the rest are released together with the batch
+#[derive(Debug)]
+pub struct WebViewEvents(Vec<BatchedEvent>);
+
+#[derive(Debug)]
+enum BatchedEvent {
+    Event(WebViewEvent),
+    /// the inner event lent to the host, still owned by the batch
+    Inner {
+        event_type: WebViewEventType,
+        sequence_number: u64,
+        timestamp: u64,
+        inner: BoxedInnerEvent,
+    },
+    Taken,
+}
+
+impl WebViewEvents {
+    pub fn new(events: Vec<WebViewEvent>) -> Self {
+        Self(events.into_iter().map(BatchedEvent::Event).collect())
+    }
+
+    fn type_at(&self, index: usize) -> value_box::Result<WebViewEventType> {
+        match self.0.get(index) {
+            Some(BatchedEvent::Event(event)) => Ok(event.get_type()),
+            Some(BatchedEvent::Inner { event_type, .. }) => Ok(*event_type),
+            _ => Err(anyhow!("No event at index {}", index).into()),
+        }
+    }
+
+    fn sequence_number_at(&self, index: usize) -> value_box::Result<u64> {
+        match self.0.get(index) {
+            Some(BatchedEvent::Event(event)) => Ok(event.sequence_number()),
+            Some(BatchedEvent::Inner {
+                sequence_number, ..
+            }) => Ok(*sequence_number),
+            _ => Err(anyhow!("No event at index {}", index).into()),
+        }
+    }
+
+    fn timestamp_at(&self, index: usize) -> value_box::Result<u64> {
+        match self.0.get(index) {
+            Some(BatchedEvent::Event(event)) => Ok(event.timestamp()),
+            Some(BatchedEvent::Inner { timestamp, .. }) => Ok(*timestamp),
+            _ => Err(anyhow!("No event at index {}", index).into()),
+        }
+    }
+
+    fn take_at(&mut self, index: usize) -> value_box::Result<WebViewEvent> {
+        let batched = self
+            .0
+            .get_mut(index)
+            .ok_or_else(|| anyhow!("No event at index {}", index))?;
+        match std::mem::replace(batched, BatchedEvent::Taken) {
+            BatchedEvent::Event(event) => Ok(event),
+            other => {
+                *batched = other;
+                Err(anyhow!("The event at index {} is already taken or lent", index).into())
+            }
+        }
+    }
+
+    /// The same inner event is returned every time it is asked for
+    fn inner_at(&mut self, index: usize) -> value_box::Result<*mut c_void> {
+        let batched = self
+            .0
+            .get_mut(index)
+            .ok_or_else(|| anyhow!("No event at index {}", index))?;
+        if matches!(batched, BatchedEvent::Event(_)) {
+            if let BatchedEvent::Event(event) = std::mem::replace(batched, BatchedEvent::Taken) {
+                *batched = BatchedEvent::Inner {
+                    event_type: event.get_type(),
+                    sequence_number: event.sequence_number(),
+                    timestamp: event.timestamp(),
+                    inner: event.into_boxed_inner(),
+                };
+            }
+        }
+        match batched {
+            BatchedEvent::Inner { inner, .. } => Ok(inner.as_ptr()),
+            _ => Err(anyhow!("The event at index {} is already taken", index).into()),
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn webview_events_get_size(events: *mut ValueBox<WebViewEvents>) -> usize {
+    events.with_ref_ok(|events| events.0.len()).or_log(0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_events_get_type_at(
+    events: *mut ValueBox<WebViewEvents>,
+    index: usize,
+) -> WebViewEventType {
+    events
+        .with_ref(|events| events.type_at(index))
+        .or_log(WebViewEventType::Unknown)
+}
+
+/// Also available once the inner event at the index is lent, but not once the event is taken
+#[no_mangle]
+pub extern "C" fn webview_events_get_sequence_number_at(
+    events: *mut ValueBox<WebViewEvents>,
+    index: usize,
+) -> u64 {
+    events
+        .with_ref(|events| events.sequence_number_at(index))
+        .or_log(0)
+}
+
+/// Microseconds since the unix epoch at which the event at the index was enqueued
+#[no_mangle]
+pub extern "C" fn webview_events_get_timestamp_at(
+    events: *mut ValueBox<WebViewEvents>,
+    index: usize,
+) -> u64 {
+    events
+        .with_ref(|events| events.timestamp_at(index))
+        .or_log(0)
+}
+
+/// Moves the event out of the batch, it must then be released on its own
+#[no_mangle]
+pub extern "C" fn webview_events_take_at(
+    events: *mut ValueBox<WebViewEvents>,
+    index: usize,
+) -> *mut ValueBox<WebViewEvent> {
+    events
+        .with_mut(|events| events.take_at(index).map(|event| ValueBox::new(event)))
+        .into_raw()
+}
+
+/// Lends the inner event at the index, like `webview_event_into_inner` but owned by the batch.
+/// It must not be released, it is valid until the batch is
+#[no_mangle]
+pub extern "C" fn webview_events_into_inner_at(
+    events: *mut ValueBox<WebViewEvents>,
+    index: usize,
+) -> *mut c_void {
+    events
+        .with_mut(|events| events.inner_at(index))
+        .or_log(std::ptr::null_mut())
+}
+
+#[no_mangle]
+pub extern "C" fn webview_events_release(events: *mut ValueBox<WebViewEvents>) {
+    events.release();
+}
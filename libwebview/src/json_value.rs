@@ -0,0 +1,236 @@
+use anyhow::anyhow;
+use serde_json::Value;
+use std::sync::Arc;
+use string_box::StringBox;
+use value_box::{ReturnBoxerResult, ValueBox, ValueBoxIntoRaw, ValueBoxPointer};
+
+static NULL: Value = Value::Null;
+
+#[derive(Debug, Clone, Copy)]
+#[repr(u8)]
+pub enum JsonValueType {
+    Null,
+    Bool,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+#[derive(Debug, Clone)]
+enum JsonPathSegment {
+    Index(usize),
+    Key(String),
+}
+
+/// A node of a parsed json tree. Nodes share the tree and only remember their path in it,
+/// so navigating a large result does not copy it
+#[derive(Debug, Clone)]
+pub struct JsonValue {
+    root: Arc<Value>,
+    path: Vec<JsonPathSegment>,
+}
+
+impl JsonValue {
+    pub fn parse(json: &str) -> Option<Self> {
+        serde_json::from_str::<Value>(json).ok().map(Self::new)
+    }
+
+    pub fn new(value: Value) -> Self {
+        Self {
+            root: Arc::new(value),
+            path: vec![],
+        }
+    }
+
+    pub fn value(&self) -> &Value {
+        self.path
+            .iter()
+            .fold(Some(self.root.as_ref()), |value, segment| {
+                value.and_then(|value| match segment {
+                    JsonPathSegment::Index(index) => value.get(index),
+                    JsonPathSegment::Key(key) => value.get(key),
+                })
+            })
+            .unwrap_or(&NULL)
+    }
+
+    pub fn value_type(&self) -> JsonValueType {
+        match self.value() {
+            Value::Null => JsonValueType::Null,
+            Value::Bool(_) => JsonValueType::Bool,
+            Value::Number(_) => JsonValueType::Number,
+            Value::String(_) => JsonValueType::String,
+            Value::Array(_) => JsonValueType::Array,
+            Value::Object(_) => JsonValueType::Object,
+        }
+    }
+
+    /// The amount of items of an array or keys of an object
+    pub fn size(&self) -> usize {
+        match self.value() {
+            Value::Array(items) => items.len(),
+            Value::Object(entries) => entries.len(),
+            _ => 0,
+        }
+    }
+
+    pub fn at_index(&self, index: usize) -> Option<Self> {
+        self.value()
+            .as_array()
+            .filter(|items| index < items.len())
+            .map(|_| self.child(JsonPathSegment::Index(index)))
+    }
+
+    pub fn at_key(&self, key: &str) -> Option<Self> {
+        self.value()
+            .as_object()
+            .filter(|entries| entries.contains_key(key))
+            .map(|_| self.child(JsonPathSegment::Key(key.to_string())))
+    }
+
+    pub fn key_at(&self, index: usize) -> Option<&str> {
+        self.value()
+            .as_object()
+            .and_then(|entries| entries.keys().nth(index))
+            .map(|key| key.as_str())
+    }
+
+    fn child(&self, segment: JsonPathSegment) -> Self {
+        let mut path = self.path.clone();
+        path.push(segment);
+        Self {
+            root: self.root.clone(),
+            path,
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn webview_json_value_get_type(value: *mut ValueBox<JsonValue>) -> JsonValueType {
+    value
+        .with_ref_ok(|value| value.value_type())
+        .or_log(JsonValueType::Null)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_json_value_get_bool(value: *mut ValueBox<JsonValue>) -> bool {
+    value
+        .with_ref_ok(|value| value.value().as_bool().unwrap_or(false))
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_json_value_get_number(value: *mut ValueBox<JsonValue>) -> f64 {
+    value
+        .with_ref_ok(|value| value.value().as_f64().unwrap_or(0.0))
+        .or_log(0.0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_json_value_is_integer(value: *mut ValueBox<JsonValue>) -> bool {
+    value
+        .with_ref_ok(|value| value.value().is_i64())
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_json_value_get_integer(value: *mut ValueBox<JsonValue>) -> i64 {
+    value
+        .with_ref_ok(|value| value.value().as_i64().unwrap_or(0))
+        .or_log(0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_json_value_get_string(
+    value: *mut ValueBox<JsonValue>,
+    string: *mut ValueBox<StringBox>,
+) {
+    value
+        .with_ref(|value| {
+            string.with_mut_ok(|string| {
+                string.set_string(value.value().as_str().unwrap_or("").to_string())
+            })
+        })
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_json_value_get_length(value: *mut ValueBox<JsonValue>) -> usize {
+    value.with_ref_ok(|value| value.size()).or_log(0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_json_value_at_index(
+    value: *mut ValueBox<JsonValue>,
+    index: usize,
+) -> *mut ValueBox<JsonValue> {
+    value
+        .with_ref(|value| {
+            value
+                .at_index(index)
+                .map(|item| ValueBox::new(item))
+                .ok_or_else(|| anyhow!("No item at index {}", index).into())
+        })
+        .into_raw()
+}
+
+#[no_mangle]
+pub extern "C" fn webview_json_value_get_key_at(
+    value: *mut ValueBox<JsonValue>,
+    index: usize,
+    key: *mut ValueBox<StringBox>,
+) {
+    value
+        .with_ref(|value| {
+            key.with_mut(|key| {
+                value
+                    .key_at(index)
+                    .map(|object_key| key.set_string(object_key.to_string()))
+                    .ok_or_else(|| anyhow!("No key at index {}", index).into())
+            })
+        })
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_json_value_at_key(
+    value: *mut ValueBox<JsonValue>,
+    key: *mut ValueBox<StringBox>,
+) -> *mut ValueBox<JsonValue> {
+    value
+        .with_ref(|value| {
+            key.with_ref(|key| {
+                value
+                    .at_key(key.as_str())
+                    .map(|item| ValueBox::new(item))
+                    .ok_or_else(|| anyhow!("No value for key {}", key.as_str()).into())
+            })
+        })
+        .into_raw()
+}
+
+#[no_mangle]
+pub extern "C" fn webview_json_value_has_key(
+    value: *mut ValueBox<JsonValue>,
+    key: *mut ValueBox<StringBox>,
+) -> bool {
+    value
+        .with_ref(|value| key.with_ref_ok(|key| value.at_key(key.as_str()).is_some()))
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_json_value_to_json(
+    value: *mut ValueBox<JsonValue>,
+    json: *mut ValueBox<StringBox>,
+) {
+    value
+        .with_ref(|value| json.with_mut_ok(|json| json.set_string(value.value().to_string())))
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_json_value_release(value: *mut ValueBox<JsonValue>) {
+    value.release();
+}
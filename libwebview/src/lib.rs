@@ -1,8 +1,25 @@
 #![allow(non_snake_case)]
 
+mod archive_protocol;
+mod async_script;
 mod attributes;
+mod directory_protocol;
+mod downloads;
 mod events_handler;
+mod events_queue;
+mod json_value;
+mod navigation_decision;
+mod navigation_policy;
+mod new_window;
+mod page_messages;
+mod protocol;
+mod resource_table;
+mod rpc;
 mod script;
+mod script_arguments;
+mod script_batch;
+mod script_exception;
+mod trusted_origins;
 mod webview;
 
 use std::ffi::c_void;
@@ -0,0 +1,175 @@
+use crate::events_handler::{EventsHandler, WebViewId};
+use crate::navigation_policy::NavigationPolicy;
+
+pub type NavigationRequestId = u64;
+
+/// Holds navigation requests pending until the host answers them with `webview_navigation_decide`.
+/// Only WebKitGTK lets us keep the underlying policy decision and answer it later.
+/// While navigation requests are masked out, navigations allowed by the policy proceed.
+#[derive(Clone)]
+#[allow(dead_code)]
+pub struct HostNavigationDecider {
+    events_handler: EventsHandler,
+    webview_id: WebViewId,
+    navigation_policy: NavigationPolicy,
+}
+
+impl HostNavigationDecider {
+    pub fn new(
+        events_handler: EventsHandler,
+        webview_id: WebViewId,
+        navigation_policy: NavigationPolicy,
+    ) -> Self {
+        Self {
+            events_handler,
+            webview_id,
+            navigation_policy,
+        }
+    }
+
+    pub fn is_supported() -> bool {
+        cfg!(not(any(
+            target_os = "windows",
+            target_os = "macos",
+            target_os = "ios",
+            target_os = "android"
+        )))
+    }
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "android"
+)))]
+mod webkit {
+    use super::{HostNavigationDecider, NavigationRequestId};
+    use crate::events_handler::WebViewEventType;
+    use gtk::prelude::WidgetExt;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use webkit2gtk::glib::prelude::Cast;
+    use webkit2gtk::{
+        NavigationPolicyDecision, NavigationPolicyDecisionExt, PolicyDecision, PolicyDecisionExt,
+        PolicyDecisionType, URIRequestExt, WebViewExt,
+    };
+
+    static NEXT_NAVIGATION_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+    static NEXT_WEBVIEW_KEY: AtomicU64 = AtomicU64::new(1);
+
+    type PendingDecisions = HashMap<NavigationRequestId, PolicyDecision>;
+
+    thread_local! {
+        // policy decisions are GObjects and must stay on the GTK thread.
+        // They are grouped by webview, the decisions of a destroyed one are ignored
+        static PENDING_DECISIONS: RefCell<HashMap<u64, PendingDecisions>> = Default::default();
+    }
+
+    impl HostNavigationDecider {
+        pub fn connect(self, webview: &webkit2gtk::WebView) {
+            let webview_key = NEXT_WEBVIEW_KEY.fetch_add(1, Ordering::Relaxed);
+            webview.connect_destroy(move |_webview| ignore_pending_decisions(webview_key));
+
+            webview.connect_decide_policy(move |_webview, decision, decision_type| {
+                if decision_type != PolicyDecisionType::NavigationAction {
+                    return false;
+                }
+
+                let Some(url) = decision
+                    .dynamic_cast_ref::<NavigationPolicyDecision>()
+                    .and_then(|decision| decision.navigation_action())
+                    .and_then(|action| action.request())
+                    .and_then(|request| request.uri())
+                    .map(|url| url.to_string())
+                else {
+                    return false;
+                };
+
+                if !self.navigation_policy.is_allowed(&url) {
+                    decision.ignore();
+                    self.events_handler
+                        .enqueue_navigation_blocked(self.webview_id, url);
+                    return true;
+                }
+
+                // nobody would answer a request the host is not subscribed to,
+                // so the policy alone decides
+                if !self
+                    .events_handler
+                    .is_enabled(WebViewEventType::NavigationRequest)
+                {
+                    decision.use_();
+                    return true;
+                }
+
+                let request_id = NEXT_NAVIGATION_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+                PENDING_DECISIONS.with(|pending| {
+                    pending
+                        .borrow_mut()
+                        .entry(webview_key)
+                        .or_default()
+                        .insert(request_id, decision.clone());
+                });
+                self.events_handler
+                    .enqueue_navigation_request(self.webview_id, request_id, url);
+
+                // keeping a reference to the decision and returning true lets us answer it later
+                true
+            });
+        }
+    }
+
+    fn ignore_pending_decisions(webview_key: u64) {
+        let decisions = PENDING_DECISIONS.with(|pending| pending.borrow_mut().remove(&webview_key));
+        for decision in decisions
+            .into_iter()
+            .flat_map(|decisions| decisions.into_values())
+        {
+            decision.ignore();
+        }
+    }
+
+    pub fn decide_navigation(request_id: NavigationRequestId, allow: bool) -> bool {
+        PENDING_DECISIONS
+            .with(|pending| {
+                pending
+                    .borrow_mut()
+                    .values_mut()
+                    .find_map(|decisions| decisions.remove(&request_id))
+            })
+            .map(|decision| {
+                if allow {
+                    decision.use_();
+                } else {
+                    decision.ignore();
+                }
+                true
+            })
+            .unwrap_or(false)
+    }
+}
+
+#[no_mangle]
+#[allow(unused)]
+pub extern "C" fn webview_navigation_decide(request_id: NavigationRequestId, allow: bool) -> bool {
+    #[cfg(not(any(
+        target_os = "windows",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "android"
+    )))]
+    {
+        webkit::decide_navigation(request_id, allow)
+    }
+    #[cfg(any(
+        target_os = "windows",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "android"
+    ))]
+    {
+        false
+    }
+}
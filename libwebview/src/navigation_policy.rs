@@ -0,0 +1,147 @@
+use regex::Regex;
+
+#[derive(Debug, Clone, Copy)]
+#[repr(u8)]
+pub enum NavigationRuleKind {
+    Scheme,
+    Host,
+    Prefix,
+    Pattern,
+}
+
+impl TryFrom<u8> for NavigationRuleKind {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Scheme),
+            1 => Ok(Self::Host),
+            2 => Ok(Self::Prefix),
+            3 => Ok(Self::Pattern),
+            _ => Err(format!("Unknown navigation rule kind {}", value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum NavigationRule {
+    Scheme(String),
+    /// A host name where `*` matches any sequence of characters and `?` a single one
+    Host(String),
+    Prefix(String),
+    Pattern(Regex),
+}
+
+impl NavigationRule {
+    pub fn new(kind: NavigationRuleKind, pattern: &str) -> Result<Self, regex::Error> {
+        Ok(match kind {
+            NavigationRuleKind::Scheme => Self::Scheme(pattern.to_ascii_lowercase()),
+            NavigationRuleKind::Host => Self::Host(pattern.to_ascii_lowercase()),
+            NavigationRuleKind::Prefix => Self::Prefix(pattern.to_string()),
+            NavigationRuleKind::Pattern => Self::Pattern(Regex::new(pattern)?),
+        })
+    }
+
+    pub fn matches(&self, url: &str) -> bool {
+        match self {
+            Self::Scheme(scheme) => url_scheme(url)
+                .map(|url_scheme| url_scheme.eq_ignore_ascii_case(scheme))
+                .unwrap_or(false),
+            Self::Host(host) => url_host(url)
+                .map(|url_host| glob_matches(host, &url_host.to_ascii_lowercase()))
+                .unwrap_or(false),
+            Self::Prefix(prefix) => url.starts_with(prefix.as_str()),
+            Self::Pattern(regex) => regex.is_match(url),
+        }
+    }
+}
+
+/// Deny rules take precedence; once an allow rule exists only matching urls are allowed
+#[derive(Debug, Clone, Default)]
+pub struct NavigationPolicy {
+    allowed: Vec<NavigationRule>,
+    denied: Vec<NavigationRule>,
+}
+
+impl NavigationPolicy {
+    pub fn allow(&mut self, rule: NavigationRule) {
+        self.allowed.push(rule);
+    }
+
+    pub fn deny(&mut self, rule: NavigationRule) {
+        self.denied.push(rule);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allowed.is_empty() && self.denied.is_empty()
+    }
+
+    pub fn is_allowed(&self, url: &str) -> bool {
+        if self.denied.iter().any(|rule| rule.matches(url)) {
+            return false;
+        }
+        self.allowed.is_empty() || self.allowed.iter().any(|rule| rule.matches(url))
+    }
+}
+
+fn url_scheme(url: &str) -> Option<&str> {
+    url.split_once(':')
+        .map(|(scheme, _)| scheme)
+        .filter(|scheme| {
+            !scheme.is_empty()
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        })
+}
+
+fn url_host(url: &str) -> Option<&str> {
+    let (_, rest) = url.split_once("://")?;
+    let authority = rest
+        .split(|c| matches!(c, '/' | '?' | '#'))
+        .next()
+        .unwrap_or_default();
+    let host_and_port = authority
+        .rsplit_once('@')
+        .map(|(_, host_and_port)| host_and_port)
+        .unwrap_or(authority);
+
+    let host = if let Some(ipv6) = host_and_port.strip_prefix('[') {
+        ipv6.split(']').next().unwrap_or_default()
+    } else {
+        host_and_port.split(':').next().unwrap_or_default()
+    };
+
+    Some(host).filter(|host| !host.is_empty())
+}
+
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    let (mut pattern_index, mut text_index) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while text_index < text.len() {
+        match pattern.get(pattern_index) {
+            Some('*') => {
+                backtrack = Some((pattern_index, text_index));
+                pattern_index += 1;
+            }
+            Some(&c) if c == '?' || c == text[text_index] => {
+                pattern_index += 1;
+                text_index += 1;
+            }
+            _ => match backtrack {
+                Some((star_index, star_text_index)) => {
+                    pattern_index = star_index + 1;
+                    text_index = star_text_index + 1;
+                    backtrack = Some((star_index, star_text_index + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[pattern_index..].iter().all(|&c| c == '*')
+}
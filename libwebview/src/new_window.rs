@@ -0,0 +1,104 @@
+use serde_json::Value;
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use wry::WebView;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum NewWindowPolicy {
+    #[default]
+    Deny,
+    LoadInSameWebView,
+    DecidedByHost,
+}
+
+impl TryFrom<u8> for NewWindowPolicy {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Deny),
+            1 => Ok(Self::LoadInSameWebView),
+            2 => Ok(Self::DecidedByHost),
+            _ => Err(format!("Unknown new window policy {}", value)),
+        }
+    }
+}
+
+const NEW_WINDOW_KEY: &str = "__libwebview_new_window";
+
+/// Posts `window.open` calls that open a new window over IPC together with their features,
+/// because the new-window hook of the webview only knows the url.
+/// Links and named targets are still reported by the hook, without features
+pub const NEW_WINDOW_SCRIPT: &str = r#"(function () {
+    var ipc = window.__libwebview_ipc;
+    var open = window.open;
+    if (!ipc || !open) {
+        return;
+    }
+    var stringify = JSON.stringify;
+    var URL = window.URL;
+    window.open = function (url, target, features) {
+        var name = target === undefined ? "" : String(target);
+        if (name !== "" && name !== "_blank") {
+            return open.apply(window, arguments);
+        }
+        var href;
+        try {
+            href = url === undefined || url === ""
+                ? "about:blank"
+                : new URL(String(url), document.baseURI).href;
+        } catch (error) {
+            return open.apply(window, arguments);
+        }
+        ipc.postMessage(stringify({
+            __libwebview_new_window: {
+                url: href,
+                features: features === undefined ? "" : String(features)
+            }
+        }));
+        return null;
+    };
+})();"#;
+
+/// A new window requested by `NEW_WINDOW_SCRIPT`
+#[derive(Debug, Clone)]
+pub struct NewWindowRequest {
+    pub url: String,
+    pub features: String,
+}
+
+impl NewWindowRequest {
+    pub fn parse(message: &str) -> Option<Self> {
+        if !message.contains(NEW_WINDOW_KEY) {
+            return None;
+        }
+        let message: Value = serde_json::from_str(message).ok()?;
+        let request = message.get(NEW_WINDOW_KEY)?;
+        Some(Self {
+            url: request.get("url")?.as_str()?.to_string(),
+            features: request.get("features")?.as_str()?.to_string(),
+        })
+    }
+}
+
+/// Loads urls of denied new windows into the webview that requested them.
+/// The webview is only known once built, so the loader is attached afterwards.
+#[derive(Clone, Default)]
+pub struct SameWebViewLoader(Rc<RefCell<Weak<WebView>>>);
+
+impl SameWebViewLoader {
+    /// Returns false if the loader is not attached or the url could not be loaded
+    pub fn load(&self, url: &str) -> bool {
+        self.0
+            .borrow()
+            .upgrade()
+            .map(|webview| webview.load_url(url).is_ok())
+            .unwrap_or(false)
+    }
+
+    /// A weak reference, the webview owns the handler that owns this loader
+    pub fn attach(&self, webview: &Rc<WebView>) {
+        *self.0.borrow_mut() = Rc::downgrade(webview);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::trusted_origins::uri_origin;
+use serde_json::Value;
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+use wry::http::Uri;
+use wry::PageLoadEvent;
+
+const MESSAGES_KEY: &str = "__libwebview_messages";
+
+/// Lets page scripts listen to messages posted by the host with `webview_post_message`:
+/// `window.host.addMessageListener(channel, listener)` and `window.host.removeMessageListener`
+pub const MESSAGE_BRIDGE_SCRIPT: &str = r#"(function () {
+    if (window.__libwebview_messages) {
+        return;
+    }
+    var listeners = new Map();
+
+    Object.defineProperty(window, "__libwebview_messages", {
+        value: Object.freeze({
+            dispatch: function (channel, data) {
+                var channelListeners = listeners.get(channel);
+                if (!channelListeners) {
+                    return;
+                }
+                var event = Object.freeze({ type: "message", channel: channel, data: data });
+                Array.from(channelListeners).forEach(function (listener) {
+                    try {
+                        listener(event);
+                    } catch (error) {
+                        console.error(error);
+                    }
+                });
+            }
+        })
+    });
+
+    window.host = window.host || {};
+    window.host.addMessageListener = function (channel, listener) {
+        if (!listeners.has(channel)) {
+            listeners.set(channel, new Set());
+        }
+        listeners.get(channel).add(listener);
+    };
+    window.host.removeMessageListener = function (channel, listener) {
+        var channelListeners = listeners.get(channel);
+        if (channelListeners) {
+            channelListeners.delete(listener);
+        }
+    };
+})();"#;
+
+/// Delivers host messages to the page, keeping them queued while the page is loading.
+/// Queued messages are meant for the origin of the page that was loading when they were
+/// posted, they are dropped once a page of another origin starts loading.
+/// The webview is only known once built, so it is attached afterwards.
+#[derive(Clone, Default)]
+pub struct PageMessenger(Rc<PageMessengerData>);
+
+#[derive(Default)]
+struct PageMessengerData {
+    webview: RefCell<Weak<wry::WebView>>,
+    is_page_ready: Cell<bool>,
+    /// unknown until the first page starts loading
+    page_origin: RefCell<Option<String>>,
+    pending_messages: RefCell<Vec<PendingMessage>>,
+}
+
+struct PendingMessage {
+    origin: Option<String>,
+    script: String,
+}
+
+impl PageMessenger {
+    pub fn attach(&self, webview: &Rc<wry::WebView>) {
+        *self.0.webview.borrow_mut() = Rc::downgrade(webview);
+        if self.0.is_page_ready.get() {
+            self.flush();
+        }
+    }
+
+    pub fn page_load(&self, event: &PageLoadEvent, url: &str) {
+        match event {
+            PageLoadEvent::Started => {
+                self.0.is_page_ready.set(false);
+                let origin = url
+                    .parse::<Uri>()
+                    .map(|uri| uri_origin(&uri))
+                    .unwrap_or_else(|_| "null".to_string());
+                // messages posted before the first page are meant for it
+                self.0.pending_messages.borrow_mut().retain_mut(|message| {
+                    *message.origin.get_or_insert_with(|| origin.clone()) == origin
+                });
+                *self.0.page_origin.borrow_mut() = Some(origin);
+            }
+            PageLoadEvent::Finished => {
+                self.0.is_page_ready.set(true);
+                self.flush();
+            }
+        }
+    }
+
+    pub fn post(&self, channel: &str, data: &Value) -> wry::Result<()> {
+        let script = format!(
+            "window.{key} && window.{key}.dispatch({channel}, {data});",
+            key = MESSAGES_KEY,
+            channel = Value::from(channel),
+            data = data
+        );
+
+        if self.0.is_page_ready.get() {
+            if let Some(webview) = self.0.webview.borrow().upgrade() {
+                return webview.evaluate_script(&script);
+            }
+        }
+
+        self.0.pending_messages.borrow_mut().push(PendingMessage {
+            origin: self.0.page_origin.borrow().clone(),
+            script,
+        });
+        Ok(())
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.0.pending_messages.borrow().len()
+    }
+
+    fn flush(&self) {
+        let Some(webview) = self.0.webview.borrow().upgrade() else {
+            return;
+        };
+        let messages = std::mem::take(&mut *self.0.pending_messages.borrow_mut());
+        for message in messages {
+            let _ = webview.evaluate_script(&message.script);
+        }
+    }
+}
@@ -0,0 +1,441 @@
+use anyhow::anyhow;
+use percent_encoding::percent_decode_str;
+use std::borrow::Cow;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use string_box::StringBox;
+use value_box::{ReturnBoxerResult, ValueBox, ValueBoxPointer};
+use wry::http::header::{ACCEPT_RANGES, CONTENT_RANGE, CONTENT_TYPE, RANGE};
+use wry::http::{HeaderMap, HeaderName, HeaderValue, Response, StatusCode};
+use wry::RequestAsyncResponder;
+
+pub type ProtocolResponse = Response<Cow<'static, [u8]>>;
+
+pub fn guess_mime_type(path: impl AsRef<Path>) -> String {
+    mime_guess::from_path(path)
+        .first_or_octet_stream()
+        .to_string()
+}
+
+pub fn status_response(status: StatusCode) -> ProtocolResponse {
+    Response::builder()
+        .status(status)
+        .body(Cow::Owned(Vec::new()))
+        .unwrap()
+}
+
+pub fn content_response(content: Vec<u8>, content_type: &str) -> ProtocolResponse {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, content_type)
+        .header(ACCEPT_RANGES, "bytes")
+        .body(Cow::Owned(content))
+        .unwrap()
+}
+
+/// A single byte range requested with the `Range` header, bounds are inclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestedRange {
+    Whole,
+    Part { start: u64, end: u64 },
+    Unsatisfiable,
+}
+
+impl RequestedRange {
+    /// Multiple or malformed ranges are ignored and the whole content is served instead
+    pub fn from_headers(headers: &HeaderMap, length: u64) -> Self {
+        let Some(range) = headers.get(RANGE).and_then(|value| value.to_str().ok()) else {
+            return Self::Whole;
+        };
+        let Some(range) = range.trim().strip_prefix("bytes=") else {
+            return Self::Whole;
+        };
+        if range.contains(',') {
+            return Self::Whole;
+        }
+        let Some((start, end)) = range.split_once('-') else {
+            return Self::Whole;
+        };
+        let (start, end) = (start.trim(), end.trim());
+
+        if start.is_empty() {
+            let Ok(suffix_length) = end.parse::<u64>() else {
+                return Self::Whole;
+            };
+            if suffix_length == 0 || length == 0 {
+                return Self::Unsatisfiable;
+            }
+            return Self::Part {
+                start: length - suffix_length.min(length),
+                end: length - 1,
+            };
+        }
+
+        let Ok(start) = start.parse::<u64>() else {
+            return Self::Whole;
+        };
+        let end = if end.is_empty() {
+            None
+        } else {
+            match end.parse::<u64>() {
+                Ok(end) if end >= start => Some(end),
+                _ => return Self::Whole,
+            }
+        };
+
+        if start >= length {
+            return Self::Unsatisfiable;
+        }
+
+        Self::Part {
+            start,
+            end: end.map_or(length - 1, |end| end.min(length - 1)),
+        }
+    }
+}
+
+/// Turns a response carrying the requested part of the content into `206 Partial Content`
+pub fn partial_response(
+    mut response: ProtocolResponse,
+    start: u64,
+    end: u64,
+    length: u64,
+) -> ProtocolResponse {
+    *response.status_mut() = StatusCode::PARTIAL_CONTENT;
+    response.headers_mut().insert(
+        CONTENT_RANGE,
+        HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end, length)).unwrap(),
+    );
+    response
+}
+
+pub fn unsatisfiable_range_response(length: u64) -> ProtocolResponse {
+    Response::builder()
+        .status(StatusCode::RANGE_NOT_SATISFIABLE)
+        .header(CONTENT_RANGE, format!("bytes */{}", length))
+        .body(Cow::Owned(Vec::new()))
+        .unwrap()
+}
+
+/// Honours the `Range` header of a request for a successful response holding the whole content
+pub fn apply_request_range(
+    request_headers: &HeaderMap,
+    mut response: ProtocolResponse,
+) -> ProtocolResponse {
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    response
+        .headers_mut()
+        .insert(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+
+    let length = response.body().len() as u64;
+    match RequestedRange::from_headers(request_headers, length) {
+        RequestedRange::Whole => response,
+        RequestedRange::Part { start, end } => {
+            let content = response.body()[start as usize..=end as usize].to_vec();
+            *response.body_mut() = Cow::Owned(content);
+            partial_response(response, start, end, length)
+        }
+        RequestedRange::Unsatisfiable => unsatisfiable_range_response(length),
+    }
+}
+
+/// Turns the percent-encoded path of a request uri into a relative path,
+/// refusing any component that could escape the root it is joined to.
+pub fn relative_request_path(uri_path: &str) -> Result<PathBuf, StatusCode> {
+    let decoded = percent_decode_str(uri_path)
+        .decode_utf8()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let mut relative_path = PathBuf::new();
+    for segment in decoded.split('/') {
+        if segment.is_empty() || segment == "." {
+            continue;
+        }
+        if segment == ".." || segment.contains('\\') {
+            return Err(StatusCode::FORBIDDEN);
+        }
+        let mut components = Path::new(segment).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => relative_path.push(segment),
+            _ => return Err(StatusCode::FORBIDDEN),
+        }
+    }
+    Ok(relative_path)
+}
+
+/// Normalizes a request uri path into a `/` separated name relative to the root
+pub fn request_entry_name(uri_path: &str) -> Result<String, StatusCode> {
+    relative_request_path(uri_path).map(|relative_path| {
+        relative_path
+            .iter()
+            .map(|component| component.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    })
+}
+
+#[derive(Clone)]
+pub struct ProtocolResponder(Arc<ProtocolResponderData>);
+
+struct ProtocolResponderData {
+    responder: Mutex<Option<RequestAsyncResponder>>,
+    headers: Mutex<HeaderMap>,
+}
+
+impl ProtocolResponder {
+    pub fn new(responder: RequestAsyncResponder) -> Self {
+        Self(Arc::new(ProtocolResponderData {
+            responder: Mutex::new(Some(responder)),
+            headers: Default::default(),
+        }))
+    }
+
+    pub fn add_header(&self, name: HeaderName, value: HeaderValue) {
+        self.0.headers.lock().unwrap().append(name, value);
+    }
+
+    pub fn is_responded(&self) -> bool {
+        self.0.responder.lock().unwrap().is_none()
+    }
+
+    /// Completes the request, returning false if it was already answered.
+    pub fn respond(&self, status: StatusCode, body: Vec<u8>) -> bool {
+        let responder = self.0.responder.lock().unwrap().take();
+        match responder {
+            None => false,
+            Some(responder) => {
+                let mut response = Response::builder()
+                    .status(status)
+                    .body(Cow::Owned(body))
+                    .unwrap();
+                *response.headers_mut() = std::mem::take(&mut *self.0.headers.lock().unwrap());
+                responder.respond(response);
+                true
+            }
+        }
+    }
+}
+
+impl Drop for ProtocolResponderData {
+    fn drop(&mut self) {
+        // the webview waits for an answer forever, make sure it gets one
+        if let Some(responder) = self.responder.get_mut().unwrap().take() {
+            responder.respond(status_response(StatusCode::INTERNAL_SERVER_ERROR));
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn webview_protocol_responder_add_header(
+    responder: *mut ValueBox<ProtocolResponder>,
+    header_name: *mut ValueBox<StringBox>,
+    header_value: *mut ValueBox<StringBox>,
+) -> bool {
+    responder
+        .with_ref(|responder| {
+            header_name.with_ref(|name| {
+                header_value.with_ref(|value| {
+                    HeaderName::from_str(name.as_str())
+                        .map_err(|error| anyhow!(error).into())
+                        .and_then(|header_name| {
+                            HeaderValue::from_str(value.as_str())
+                                .map_err(|error| anyhow!(error).into())
+                                .map(|header_value| {
+                                    responder.add_header(header_name, header_value);
+                                    true
+                                })
+                        })
+                })
+            })
+        })
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_protocol_responder_respond(
+    responder: *mut ValueBox<ProtocolResponder>,
+    status: u16,
+    body: *const u8,
+    length: usize,
+) -> bool {
+    responder
+        .with_ref(|responder| {
+            StatusCode::from_u16(status)
+                .map_err(|error| anyhow!(error).into())
+                .map(|status| {
+                    let body = if body.is_null() || length == 0 {
+                        Vec::new()
+                    } else {
+                        unsafe { std::slice::from_raw_parts(body, length) }.to_vec()
+                    };
+                    responder.respond(status, body)
+                })
+        })
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_protocol_responder_is_responded(
+    responder: *mut ValueBox<ProtocolResponder>,
+) -> bool {
+    responder
+        .with_ref_ok(|responder| responder.is_responded())
+        .or_log(true)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_protocol_responder_release(responder: *mut ValueBox<ProtocolResponder>) {
+    responder.release();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(value: &'static str, length: u64) -> RequestedRange {
+        let mut headers = HeaderMap::new();
+        headers.insert(RANGE, HeaderValue::from_static(value));
+        RequestedRange::from_headers(&headers, length)
+    }
+
+    #[test]
+    fn whole_without_range() {
+        assert_eq!(
+            RequestedRange::from_headers(&HeaderMap::new(), 10),
+            RequestedRange::Whole
+        );
+    }
+
+    #[test]
+    fn bounded_range() {
+        assert_eq!(
+            range("bytes=2-5", 10),
+            RequestedRange::Part { start: 2, end: 5 }
+        );
+        assert_eq!(
+            range("bytes=5-100", 10),
+            RequestedRange::Part { start: 5, end: 9 }
+        );
+        assert_eq!(
+            range("bytes=5-", 10),
+            RequestedRange::Part { start: 5, end: 9 }
+        );
+    }
+
+    #[test]
+    fn suffix_range() {
+        assert_eq!(
+            range("bytes=-3", 10),
+            RequestedRange::Part { start: 7, end: 9 }
+        );
+        assert_eq!(
+            range("bytes=-20", 10),
+            RequestedRange::Part { start: 0, end: 9 }
+        );
+        assert_eq!(range("bytes=-0", 10), RequestedRange::Unsatisfiable);
+    }
+
+    #[test]
+    fn start_past_length() {
+        assert_eq!(range("bytes=10-", 10), RequestedRange::Unsatisfiable);
+        assert_eq!(range("bytes=12-20", 10), RequestedRange::Unsatisfiable);
+    }
+
+    #[test]
+    fn end_before_start() {
+        assert_eq!(range("bytes=5-3", 10), RequestedRange::Whole);
+    }
+
+    #[test]
+    fn multiple_or_malformed_ranges() {
+        assert_eq!(range("bytes=0-1,3-4", 10), RequestedRange::Whole);
+        assert_eq!(range("items=0-1", 10), RequestedRange::Whole);
+        assert_eq!(range("bytes=a-b", 10), RequestedRange::Whole);
+    }
+
+    #[test]
+    fn zero_length_body() {
+        assert_eq!(range("bytes=0-", 0), RequestedRange::Unsatisfiable);
+        assert_eq!(range("bytes=-5", 0), RequestedRange::Unsatisfiable);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(RANGE, HeaderValue::from_static("bytes=0-0"));
+        let response = apply_request_range(&headers, content_response(vec![], "text/plain"));
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    }
+
+    #[test]
+    fn partial_content() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RANGE, HeaderValue::from_static("bytes=-2"));
+        let response = apply_request_range(&headers, content_response(b"abcd".to_vec(), ""));
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.body().as_ref(), b"cd");
+        assert_eq!(response.headers()[CONTENT_RANGE], "bytes 2-3/4");
+    }
+
+    #[test]
+    fn relative_path() {
+        assert_eq!(
+            relative_request_path("/a/b.txt"),
+            Ok(PathBuf::from("a").join("b.txt"))
+        );
+        assert_eq!(relative_request_path("//./a//"), Ok(PathBuf::from("a")));
+        assert_eq!(
+            request_entry_name("/a/%20b.txt"),
+            Ok("a/ b.txt".to_string())
+        );
+    }
+
+    #[test]
+    fn parent_components_are_forbidden() {
+        assert_eq!(
+            relative_request_path("/../secret"),
+            Err(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            relative_request_path("/a/../../secret"),
+            Err(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            relative_request_path("/%2e%2e/secret"),
+            Err(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            relative_request_path("/a/%2E%2E/b"),
+            Err(StatusCode::FORBIDDEN)
+        );
+    }
+
+    #[test]
+    fn backslashes_are_forbidden() {
+        assert_eq!(
+            relative_request_path("/a\\..\\b"),
+            Err(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(relative_request_path("/a%5Cb"), Err(StatusCode::FORBIDDEN));
+        assert_eq!(
+            relative_request_path("/%5C%5C?%5CC:%5C"),
+            Err(StatusCode::FORBIDDEN)
+        );
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn windows_prefixes_are_forbidden() {
+        assert_eq!(
+            relative_request_path("/C:/Windows"),
+            Err(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(relative_request_path("/%43:"), Err(StatusCode::FORBIDDEN));
+    }
+
+    #[test]
+    fn invalid_utf8_is_a_bad_request() {
+        assert_eq!(relative_request_path("/%ff"), Err(StatusCode::BAD_REQUEST));
+    }
+}
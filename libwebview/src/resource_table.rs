@@ -0,0 +1,176 @@
+use crate::protocol::{apply_request_range, request_entry_name, status_response, ProtocolResponse};
+use anyhow::anyhow;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use string_box::StringBox;
+use value_box::{ReturnBoxerResult, ValueBox, ValueBoxPointer};
+use wry::http::header::CONTENT_TYPE;
+use wry::http::{HeaderMap, HeaderName, HeaderValue, Request, Response, StatusCode};
+
+#[derive(Clone, Default)]
+pub struct ResourceTable(Arc<Mutex<HashMap<String, Resource>>>);
+
+#[derive(Debug, Clone)]
+pub struct Resource {
+    content: Arc<Vec<u8>>,
+    content_type: HeaderValue,
+    status: StatusCode,
+    headers: HeaderMap,
+}
+
+impl ResourceTable {
+    pub fn add_resource(&self, path: &str, resource: Resource) -> Result<(), StatusCode> {
+        let name = request_entry_name(path)?;
+        self.0.lock().unwrap().insert(name, resource);
+        Ok(())
+    }
+
+    pub fn add_header(
+        &self,
+        path: &str,
+        name: HeaderName,
+        value: HeaderValue,
+    ) -> Result<(), StatusCode> {
+        let entry_name = request_entry_name(path)?;
+        self.0
+            .lock()
+            .unwrap()
+            .get_mut(&entry_name)
+            .map(|resource| {
+                resource.headers.append(name, value);
+            })
+            .ok_or(StatusCode::NOT_FOUND)
+    }
+
+    pub fn remove_resource(&self, path: &str) -> bool {
+        request_entry_name(path)
+            .map(|name| self.0.lock().unwrap().remove(&name).is_some())
+            .unwrap_or(false)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+
+    pub fn handle(&self, request: &Request<Vec<u8>>) -> ProtocolResponse {
+        request_entry_name(request.uri().path())
+            .and_then(|name| {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .get(&name)
+                    .cloned()
+                    .ok_or(StatusCode::NOT_FOUND)
+            })
+            .map(|resource| apply_request_range(request.headers(), resource.into_response()))
+            .unwrap_or_else(status_response)
+    }
+}
+
+impl Resource {
+    pub fn new(content: Vec<u8>, content_type: HeaderValue, status: StatusCode) -> Self {
+        Self {
+            content: Arc::new(content),
+            content_type,
+            status,
+            headers: Default::default(),
+        }
+    }
+
+    fn into_response(self) -> ProtocolResponse {
+        let mut response = Response::builder()
+            .status(self.status)
+            .body(Cow::Owned(self.content.as_ref().clone()))
+            .unwrap();
+        *response.headers_mut() = self.headers;
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, self.content_type);
+        response
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn webview_resource_table_new() -> *mut ValueBox<ResourceTable> {
+    ValueBox::new(ResourceTable::default()).into_raw()
+}
+
+#[no_mangle]
+pub extern "C" fn webview_resource_table_add(
+    table: *mut ValueBox<ResourceTable>,
+    path: *mut ValueBox<StringBox>,
+    content: *const u8,
+    length: usize,
+    content_type: *mut ValueBox<StringBox>,
+    status: u16,
+) -> bool {
+    table
+        .with_ref(|table| {
+            path.with_ref(|path| {
+                content_type.with_ref(|content_type| {
+                    let content_type = HeaderValue::from_str(content_type.as_str())
+                        .map_err(|error| anyhow!(error))?;
+                    let status = StatusCode::from_u16(status).map_err(|error| anyhow!(error))?;
+                    let content = if content.is_null() || length == 0 {
+                        Vec::new()
+                    } else {
+                        unsafe { std::slice::from_raw_parts(content, length) }.to_vec()
+                    };
+                    table
+                        .add_resource(path.as_str(), Resource::new(content, content_type, status))
+                        .map_err(|status| anyhow!("Invalid resource path: {}", status).into())
+                        .map(|_| true)
+                })
+            })
+        })
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_resource_table_add_header(
+    table: *mut ValueBox<ResourceTable>,
+    path: *mut ValueBox<StringBox>,
+    header_name: *mut ValueBox<StringBox>,
+    header_value: *mut ValueBox<StringBox>,
+) -> bool {
+    table
+        .with_ref(|table| {
+            path.with_ref(|path| {
+                header_name.with_ref(|name| {
+                    header_value.with_ref(|value| {
+                        let header_name =
+                            HeaderName::from_str(name.as_str()).map_err(|error| anyhow!(error))?;
+                        let header_value = HeaderValue::from_str(value.as_str())
+                            .map_err(|error| anyhow!(error))?;
+                        table
+                            .add_header(path.as_str(), header_name, header_value)
+                            .map_err(|status| anyhow!("Unknown resource path: {}", status).into())
+                            .map(|_| true)
+                    })
+                })
+            })
+        })
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_resource_table_remove(
+    table: *mut ValueBox<ResourceTable>,
+    path: *mut ValueBox<StringBox>,
+) -> bool {
+    table
+        .with_ref(|table| path.with_ref_ok(|path| table.remove_resource(path.as_str())))
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_resource_table_get_size(table: *mut ValueBox<ResourceTable>) -> usize {
+    table.with_ref_ok(|table| table.len()).or_log(0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_resource_table_release(table: *mut ValueBox<ResourceTable>) {
+    table.release();
+}
@@ -0,0 +1,127 @@
+use crate::webview::WebView;
+use anyhow::anyhow;
+use serde_json::Value;
+use string_box::StringBox;
+use value_box::{ReturnBoxerResult, ValueBox, ValueBoxPointer};
+
+pub type RpcCallId = u64;
+
+const RPC_KEY: &str = "__libwebview_rpc";
+
+/// Exposes `window.host.call(method, params)` which posts an IPC message and returns a Promise
+/// settled later by `webview_rpc_resolve` or `webview_rpc_reject`
+pub const RPC_BRIDGE_SCRIPT: &str = r#"(function () {
+    if (window.__libwebview_rpc) {
+        return;
+    }
+    var pendingCalls = new Map();
+    var nextCallId = 1;
+
+    function settle(callId, isResolved, value) {
+        var call = pendingCalls.get(callId);
+        if (!call) {
+            return;
+        }
+        pendingCalls.delete(callId);
+        if (isResolved) {
+            call.resolve(value);
+        } else {
+            call.reject(value);
+        }
+    }
+
+    Object.defineProperty(window, "__libwebview_rpc", {
+        value: Object.freeze({
+            resolve: function (callId, value) { settle(callId, true, value); },
+            reject: function (callId, reason) { settle(callId, false, reason); }
+        })
+    });
+
+    window.host = window.host || {};
+    window.host.call = function (method, params) {
+        return new Promise(function (resolve, reject) {
+            var callId = nextCallId++;
+            pendingCalls.set(callId, { resolve: resolve, reject: reject });
+            try {
+                (window.__libwebview_ipc || window.ipc).postMessage(JSON.stringify({
+                    __libwebview_rpc: { id: callId, method: String(method) },
+                    params: params === undefined ? null : params
+                }));
+            } catch (error) {
+                pendingCalls.delete(callId);
+                reject(error);
+            }
+        });
+    };
+})();"#;
+
+#[derive(Debug, Clone)]
+pub struct RpcCall {
+    pub id: RpcCallId,
+    pub method: String,
+    pub params: String,
+}
+
+impl RpcCall {
+    /// Recognizes IPC messages posted by `window.host.call`
+    pub fn parse(message: &str) -> Option<Self> {
+        if !message.contains(RPC_KEY) {
+            return None;
+        }
+        let message: Value = serde_json::from_str(message).ok()?;
+        let call = message.get(RPC_KEY)?;
+        Some(Self {
+            id: call.get("id")?.as_u64()?,
+            method: call.get("method")?.as_str()?.to_string(),
+            params: message
+                .get("params")
+                .map(|params| params.to_string())
+                .unwrap_or_else(|| Value::Null.to_string()),
+        })
+    }
+}
+
+fn settle_call(
+    webview: *mut ValueBox<WebView>,
+    call_id: RpcCallId,
+    json: *mut ValueBox<StringBox>,
+    settle_function: &str,
+) -> bool {
+    webview
+        .with_ref(|webview| {
+            json.with_ref(|json| {
+                // re-serializing guarantees that only a json value ends up in the script
+                let value =
+                    serde_json::from_str::<Value>(json.as_str()).map_err(|error| anyhow!(error))?;
+                webview
+                    .evaluate_script(&format!(
+                        "window.{key} && window.{key}.{function}({id}, {value});",
+                        key = RPC_KEY,
+                        function = settle_function,
+                        id = call_id,
+                        value = value
+                    ))
+                    .map_err(|error| anyhow!(error).into())
+                    .map(|_| true)
+            })
+        })
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_rpc_resolve(
+    webview: *mut ValueBox<WebView>,
+    call_id: RpcCallId,
+    json: *mut ValueBox<StringBox>,
+) -> bool {
+    settle_call(webview, call_id, json, "resolve")
+}
+
+#[no_mangle]
+pub extern "C" fn webview_rpc_reject(
+    webview: *mut ValueBox<WebView>,
+    call_id: RpcCallId,
+    json: *mut ValueBox<StringBox>,
+) -> bool {
+    settle_call(webview, call_id, json, "reject")
+}
@@ -1,4 +1,13 @@
-use std::sync::{Arc, Mutex};
+use crate::json_value::JsonValue;
+use crate::script_arguments::ScriptArguments;
+use crate::script_batch::ScriptBatchCompletion;
+use crate::script_exception::ScriptException;
+use anyhow::anyhow;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::sync::{Arc, Condvar, Mutex, OnceLock, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
 use string_box::StringBox;
 use value_box::{ReturnBoxerResult, ValueBox, ValueBoxIntoRaw, ValueBoxPointer};
 
@@ -6,28 +15,181 @@ use value_box::{ReturnBoxerResult, ValueBox, ValueBoxIntoRaw, ValueBoxPointer};
 pub struct ScriptToEvaluate(Arc<ScriptToEvaluateData>);
 
 impl ScriptToEvaluate {
+    pub fn new(script: &str, completion: ScriptCompletion) -> Self {
+        Self(Arc::new(ScriptToEvaluateData {
+            script: script.to_string(),
+            result: Mutex::new(ScriptToEvaluateResult::new(
+                "".to_string(),
+                ScriptEvaluationState::Pending,
+            )),
+            completion,
+        }))
+    }
+
     pub fn signal_semaphore(&self) {
-        unsafe { (self.0.semaphore_signaller)(self.0.semaphore_index) };
+        match &self.0.completion {
+            ScriptCompletion::Semaphore {
+                semaphore_index,
+                semaphore_signaller,
+            } => unsafe { (*semaphore_signaller)(*semaphore_index) },
+            ScriptCompletion::Batch(batch) => batch.script_settled(),
+        }
     }
 
     pub fn script(&self) -> &str {
         self.0.script.as_str()
     }
 
+    pub fn value_and_state(&self) -> (String, ScriptEvaluationState) {
+        let result = self.0.result.lock().unwrap();
+        (result.value.clone(), result.state)
+    }
+
+    pub fn json_value(&self) -> Option<JsonValue> {
+        self.0.result.lock().unwrap().json.clone()
+    }
+
+    pub fn is_pending(&self) -> bool {
+        matches!(
+            self.0.result.lock().unwrap().state,
+            ScriptEvaluationState::Pending
+        )
+    }
+
+    /// The value is the json serialized result of the script, it is parsed once here
     pub fn set_value(&self, value: String) {
-        *self.0.result.lock().unwrap() = ScriptToEvaluateResult {
+        let json = JsonValue::parse(&value);
+        self.settle(ScriptToEvaluateResult {
             value,
+            json,
+            exception: None,
             state: ScriptEvaluationState::Finished,
-        };
-        self.signal_semaphore();
+        });
     }
 
     pub fn set_error(&self, error: String) {
-        *self.0.result.lock().unwrap() = ScriptToEvaluateResult {
-            value: error,
+        self.settle(ScriptToEvaluateResult::new(
+            error,
+            ScriptEvaluationState::Errored,
+        ));
+    }
+
+    pub fn set_exception(&self, exception: ScriptException) {
+        self.settle(ScriptToEvaluateResult {
+            value: exception.description(),
+            json: None,
+            exception: Some(exception),
             state: ScriptEvaluationState::Errored,
-        };
+        });
+    }
+
+    pub fn exception(&self) -> Option<ScriptException> {
+        self.0.result.lock().unwrap().exception.clone()
+    }
+
+    pub fn cancel(&self) -> bool {
+        self.settle(ScriptToEvaluateResult::new(
+            "".to_string(),
+            ScriptEvaluationState::Cancelled,
+        ))
+    }
+
+    /// Times out the evaluation unless it is settled within the timeout.
+    /// Only a weak reference is kept, so a released script does not wait for its deadline
+    fn start_timeout(&self, timeout: Duration) {
+        // a deadline too far to be represented is never reached
+        if let Some(deadline) = Instant::now().checked_add(timeout) {
+            ScriptTimeouts::global().add(deadline, Arc::downgrade(&self.0));
+        }
+    }
+
+    /// Only the first outcome is kept, later ones are ignored and do not signal the semaphore
+    fn settle(&self, outcome: ScriptToEvaluateResult) -> bool {
+        {
+            let mut result = self.0.result.lock().unwrap();
+            if !matches!(result.state, ScriptEvaluationState::Pending) {
+                return false;
+            }
+            *result = outcome;
+        }
         self.signal_semaphore();
+        true
+    }
+}
+
+/// Deadlines of all scripts evaluated with a timeout, waited for by a single thread
+#[derive(Default)]
+struct ScriptTimeouts {
+    deadlines: Mutex<BinaryHeap<Reverse<ScriptDeadline>>>,
+    changed: Condvar,
+}
+
+impl ScriptTimeouts {
+    fn global() -> &'static Self {
+        static TIMEOUTS: OnceLock<ScriptTimeouts> = OnceLock::new();
+        TIMEOUTS.get_or_init(|| {
+            thread::spawn(|| ScriptTimeouts::global().run());
+            Self::default()
+        })
+    }
+
+    fn add(&self, deadline: Instant, script: Weak<ScriptToEvaluateData>) {
+        self.deadlines
+            .lock()
+            .unwrap()
+            .push(Reverse(ScriptDeadline { deadline, script }));
+        self.changed.notify_one();
+    }
+
+    fn run(&self) {
+        let mut deadlines = self.deadlines.lock().unwrap();
+        loop {
+            let next_deadline = deadlines.peek().map(|Reverse(next)| next.deadline);
+            match next_deadline {
+                Some(deadline) if deadline <= Instant::now() => {
+                    let expired = deadlines.pop().map(|Reverse(expired)| expired.script);
+                    // the semaphore is signalled without holding the lock
+                    drop(deadlines);
+                    if let Some(data) = expired.and_then(|script| script.upgrade()) {
+                        ScriptToEvaluate(data).settle(ScriptToEvaluateResult::new(
+                            "Script evaluation timed out".to_string(),
+                            ScriptEvaluationState::TimedOut,
+                        ));
+                    }
+                    deadlines = self.deadlines.lock().unwrap();
+                }
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    deadlines = self.changed.wait_timeout(deadlines, timeout).unwrap().0;
+                }
+                None => deadlines = self.changed.wait(deadlines).unwrap(),
+            }
+        }
+    }
+}
+
+struct ScriptDeadline {
+    deadline: Instant,
+    script: Weak<ScriptToEvaluateData>,
+}
+
+impl PartialEq for ScriptDeadline {
+    fn eq(&self, other: &Self) -> bool {
+        self.deadline == other.deadline
+    }
+}
+
+impl Eq for ScriptDeadline {}
+
+impl PartialOrd for ScriptDeadline {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScriptDeadline {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.deadline.cmp(&other.deadline)
     }
 }
 
@@ -35,16 +197,38 @@ impl ScriptToEvaluate {
 struct ScriptToEvaluateData {
     script: String,
     result: Mutex<ScriptToEvaluateResult>,
-    semaphore_index: usize,
-    semaphore_signaller: unsafe extern "C" fn(usize),
+    completion: ScriptCompletion,
+}
+
+/// Scripts of a batch report to the batch, which signals the semaphore once all are settled
+#[derive(Debug)]
+pub enum ScriptCompletion {
+    Semaphore {
+        semaphore_index: usize,
+        semaphore_signaller: unsafe extern "C" fn(usize),
+    },
+    Batch(Arc<ScriptBatchCompletion>),
 }
 
 #[derive(Debug)]
 struct ScriptToEvaluateResult {
     value: String,
+    json: Option<JsonValue>,
+    exception: Option<ScriptException>,
     state: ScriptEvaluationState,
 }
 
+impl ScriptToEvaluateResult {
+    fn new(value: String, state: ScriptEvaluationState) -> Self {
+        Self {
+            value,
+            json: None,
+            exception: None,
+            state,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 #[repr(u8)]
 pub enum ScriptEvaluationState {
@@ -52,6 +236,22 @@ pub enum ScriptEvaluationState {
     Finished,
     Errored,
     Released,
+    TimedOut,
+    Cancelled,
+}
+
+fn new_script_to_evaluate(
+    script: &str,
+    semaphore_index: usize,
+    semaphore_signaller: unsafe extern "C" fn(usize),
+) -> ScriptToEvaluate {
+    ScriptToEvaluate::new(
+        script,
+        ScriptCompletion::Semaphore {
+            semaphore_index,
+            semaphore_signaller,
+        },
+    )
 }
 
 #[no_mangle]
@@ -62,19 +262,62 @@ pub extern "C" fn webview_script_to_evaluate_new(
 ) -> *mut ValueBox<ScriptToEvaluate> {
     script
         .with_ref_ok(|script| {
-            ValueBox::new(ScriptToEvaluate(Arc::new(ScriptToEvaluateData {
-                script: script.to_string(),
-                result: Mutex::new(ScriptToEvaluateResult {
-                    value: "".to_string(),
-                    state: ScriptEvaluationState::Pending,
-                }),
+            ValueBox::new(new_script_to_evaluate(
+                script.as_str(),
                 semaphore_index,
                 semaphore_signaller,
-            })))
+            ))
+        })
+        .into_raw()
+}
+
+/// Invokes the function body with the arguments, which are serialized rather than concatenated
+#[no_mangle]
+pub extern "C" fn webview_script_to_evaluate_new_with_arguments(
+    function_body: *mut ValueBox<StringBox>,
+    arguments: *mut ValueBox<ScriptArguments>,
+    semaphore_index: usize,
+    semaphore_signaller: unsafe extern "C" fn(usize),
+) -> *mut ValueBox<ScriptToEvaluate> {
+    function_body
+        .with_ref(|function_body| {
+            arguments.with_ref_ok(|arguments| {
+                ValueBox::new(new_script_to_evaluate(
+                    &arguments.invocation(function_body.as_str()),
+                    semaphore_index,
+                    semaphore_signaller,
+                ))
+            })
+        })
+        .into_raw()
+}
+
+/// The evaluation becomes `TimedOut` if it is not settled within `timeout_millis`
+#[no_mangle]
+pub extern "C" fn webview_script_to_evaluate_new_with_timeout(
+    script: *mut ValueBox<StringBox>,
+    timeout_millis: u64,
+    semaphore_index: usize,
+    semaphore_signaller: unsafe extern "C" fn(usize),
+) -> *mut ValueBox<ScriptToEvaluate> {
+    script
+        .with_ref_ok(|script| {
+            let script =
+                new_script_to_evaluate(script.as_str(), semaphore_index, semaphore_signaller);
+            script.start_timeout(Duration::from_millis(timeout_millis));
+            ValueBox::new(script)
         })
         .into_raw()
 }
 
+/// Returns false if the evaluation was already settled
+#[no_mangle]
+pub extern "C" fn webview_script_to_evaluate_cancel(
+    script: *mut ValueBox<ScriptToEvaluate>,
+) -> bool {
+    script.with_ref_ok(|script| script.cancel()).or_log(false)
+}
+
 #[no_mangle]
 pub extern "C" fn webview_script_to_evaluate_get_result(
     script: *mut ValueBox<ScriptToEvaluate>,
@@ -83,14 +326,109 @@ pub extern "C" fn webview_script_to_evaluate_get_result(
     script
         .with_ref(|script| {
             result.with_mut_ok(|result| {
-                let lock = script.0.result.lock().unwrap();
-                result.set_string(lock.value.clone());
-                lock.state
+                let (value, state) = script.value_and_state();
+                result.set_string(value);
+                state
             })
         })
         .or_log(ScriptEvaluationState::Released)
 }
 
+/// The parsed result of a finished script, to be navigated without parsing it again
+#[no_mangle]
+pub extern "C" fn webview_script_to_evaluate_get_json_value(
+    script: *mut ValueBox<ScriptToEvaluate>,
+) -> *mut ValueBox<JsonValue> {
+    script
+        .with_ref(|script| {
+            script
+                .json_value()
+                .map(|json| ValueBox::new(json))
+                .ok_or_else(|| anyhow!("The script did not finish with a json value").into())
+        })
+        .into_raw()
+}
+
+/// Returns false if the script did not throw
+#[no_mangle]
+pub extern "C" fn webview_script_to_evaluate_has_exception(
+    script: *mut ValueBox<ScriptToEvaluate>,
+) -> bool {
+    script
+        .with_ref_ok(|script| script.exception().is_some())
+        .or_log(false)
+}
+
+fn get_exception_text(
+    script: *mut ValueBox<ScriptToEvaluate>,
+    text: *mut ValueBox<StringBox>,
+    exception_text: impl FnOnce(ScriptException) -> String,
+) {
+    script
+        .with_ref(|script| {
+            text.with_mut(|text| {
+                script
+                    .exception()
+                    .map(|exception| text.set_string(exception_text(exception)))
+                    .ok_or_else(|| anyhow!("The script did not throw").into())
+            })
+        })
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_script_to_evaluate_get_exception_message(
+    script: *mut ValueBox<ScriptToEvaluate>,
+    message: *mut ValueBox<StringBox>,
+) {
+    get_exception_text(script, message, |exception| exception.message);
+}
+
+#[no_mangle]
+pub extern "C" fn webview_script_to_evaluate_get_exception_name(
+    script: *mut ValueBox<ScriptToEvaluate>,
+    name: *mut ValueBox<StringBox>,
+) {
+    get_exception_text(script, name, |exception| exception.name);
+}
+
+#[no_mangle]
+pub extern "C" fn webview_script_to_evaluate_get_exception_stack(
+    script: *mut ValueBox<ScriptToEvaluate>,
+    stack: *mut ValueBox<StringBox>,
+) {
+    get_exception_text(script, stack, |exception| exception.stack);
+}
+
+/// Lines and columns start at 1, 0 means the position is unknown
+#[no_mangle]
+pub extern "C" fn webview_script_to_evaluate_get_exception_line(
+    script: *mut ValueBox<ScriptToEvaluate>,
+) -> u32 {
+    script
+        .with_ref_ok(|script| {
+            script
+                .exception()
+                .map(|exception| exception.line)
+                .unwrap_or(0)
+        })
+        .or_log(0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_script_to_evaluate_get_exception_column(
+    script: *mut ValueBox<ScriptToEvaluate>,
+) -> u32 {
+    script
+        .with_ref_ok(|script| {
+            script
+                .exception()
+                .map(|exception| exception.column)
+                .unwrap_or(0)
+        })
+        .or_log(0)
+}
+
 #[no_mangle]
 pub extern "C" fn webview_script_to_evaluate_release(script: *mut ValueBox<ScriptToEvaluate>) {
     script.release();
@@ -0,0 +1,146 @@
+use anyhow::anyhow;
+use regex::Regex;
+use serde_json::Value;
+use std::sync::OnceLock;
+use string_box::StringBox;
+use value_box::{ReturnBoxerResult, ValueBox, ValueBoxPointer};
+
+/// Named arguments of a script function body. Values are only ever inserted as serialized json,
+/// so they can not change the meaning of the script
+#[derive(Debug, Clone, Default)]
+pub struct ScriptArguments(Vec<(String, Value)>);
+
+impl ScriptArguments {
+    pub fn add(&mut self, name: &str, value: Value) -> value_box::Result<()> {
+        if !is_identifier(name) {
+            return Err(anyhow!("{} is not a valid argument name", name).into());
+        }
+        if self
+            .0
+            .iter()
+            .any(|(existing_name, _)| existing_name == name)
+        {
+            return Err(anyhow!("Argument {} is already defined", name).into());
+        }
+        self.0.push((name.to_string(), value));
+        Ok(())
+    }
+
+    /// An immediately invoked function with the arguments as its parameters
+    pub fn invocation(&self, function_body: &str) -> String {
+        let names = self
+            .0
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let values = Value::Array(self.0.iter().map(|(_, value)| value.clone()).collect());
+
+        format!(
+            "(function ({names}) {{\n{body}\n}}).apply(undefined, {values});",
+            names = names,
+            body = function_body,
+            values = script_literal(&values)
+        )
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    static IDENTIFIER: OnceLock<Regex> = OnceLock::new();
+    IDENTIFIER
+        .get_or_init(|| Regex::new(r"^[A-Za-z_$][A-Za-z0-9_$]*$").unwrap())
+        .is_match(name)
+}
+
+/// Json is valid javascript, except for line and paragraph separators in older engines
+fn script_literal(value: &Value) -> String {
+    value
+        .to_string()
+        .replace('\u{2028}', "\\u2028")
+        .replace('\u{2029}', "\\u2029")
+}
+
+fn add_argument(
+    arguments: *mut ValueBox<ScriptArguments>,
+    name: *mut ValueBox<StringBox>,
+    value: Value,
+) -> bool {
+    arguments
+        .with_mut(|arguments| {
+            name.with_ref(|name| arguments.add(name.as_str(), value))
+                .map(|_| true)
+        })
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_script_arguments_new() -> *mut ValueBox<ScriptArguments> {
+    ValueBox::new(ScriptArguments::default()).into_raw()
+}
+
+#[no_mangle]
+pub extern "C" fn webview_script_arguments_add_string(
+    arguments: *mut ValueBox<ScriptArguments>,
+    name: *mut ValueBox<StringBox>,
+    value: *mut ValueBox<StringBox>,
+) -> bool {
+    value
+        .with_ref_ok(|value| Value::from(value.as_str()))
+        .map(|value| add_argument(arguments, name, value))
+        .or_log(false)
+}
+
+/// Non finite numbers are passed as null
+#[no_mangle]
+pub extern "C" fn webview_script_arguments_add_number(
+    arguments: *mut ValueBox<ScriptArguments>,
+    name: *mut ValueBox<StringBox>,
+    value: f64,
+) -> bool {
+    add_argument(arguments, name, Value::from(value))
+}
+
+#[no_mangle]
+pub extern "C" fn webview_script_arguments_add_integer(
+    arguments: *mut ValueBox<ScriptArguments>,
+    name: *mut ValueBox<StringBox>,
+    value: i64,
+) -> bool {
+    add_argument(arguments, name, Value::from(value))
+}
+
+#[no_mangle]
+pub extern "C" fn webview_script_arguments_add_bool(
+    arguments: *mut ValueBox<ScriptArguments>,
+    name: *mut ValueBox<StringBox>,
+    value: bool,
+) -> bool {
+    add_argument(arguments, name, Value::from(value))
+}
+
+#[no_mangle]
+pub extern "C" fn webview_script_arguments_add_null(
+    arguments: *mut ValueBox<ScriptArguments>,
+    name: *mut ValueBox<StringBox>,
+) -> bool {
+    add_argument(arguments, name, Value::Null)
+}
+
+/// Returns false if the json is invalid
+#[no_mangle]
+pub extern "C" fn webview_script_arguments_add_json(
+    arguments: *mut ValueBox<ScriptArguments>,
+    name: *mut ValueBox<StringBox>,
+    json: *mut ValueBox<StringBox>,
+) -> bool {
+    json.with_ref(|json| {
+        serde_json::from_str::<Value>(json.as_str()).map_err(|error| anyhow!(error).into())
+    })
+    .map(|value| add_argument(arguments, name, value))
+    .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_script_arguments_release(arguments: *mut ValueBox<ScriptArguments>) {
+    arguments.release();
+}
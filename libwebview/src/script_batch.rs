@@ -0,0 +1,167 @@
+use crate::json_value::JsonValue;
+use crate::script::{ScriptCompletion, ScriptEvaluationState, ScriptToEvaluate};
+use anyhow::anyhow;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use string_box::StringBox;
+use value_box::{ReturnBoxerResult, ValueBox, ValueBoxIntoRaw, ValueBoxPointer};
+
+/// Scripts evaluated together, the semaphore is signalled once when all of them are settled
+#[derive(Debug)]
+pub struct ScriptBatch {
+    scripts: Vec<ScriptToEvaluate>,
+    completion: Arc<ScriptBatchCompletion>,
+}
+
+#[derive(Debug)]
+pub struct ScriptBatchCompletion {
+    /// the scripts that are not settled yet, plus one until the batch is submitted
+    remaining: AtomicUsize,
+    is_submitted: AtomicBool,
+    semaphore_index: usize,
+    semaphore_signaller: unsafe extern "C" fn(usize),
+}
+
+impl ScriptBatchCompletion {
+    pub fn script_settled(&self) {
+        if self.remaining.fetch_sub(1, Ordering::SeqCst) == 1 {
+            unsafe { (self.semaphore_signaller)(self.semaphore_index) };
+        }
+    }
+}
+
+impl ScriptBatch {
+    pub fn add(&mut self, script: &str) -> value_box::Result<()> {
+        if self.is_submitted() {
+            return Err(anyhow!("Scripts can not be added to a submitted batch").into());
+        }
+        self.completion.remaining.fetch_add(1, Ordering::SeqCst);
+        self.scripts.push(ScriptToEvaluate::new(
+            script,
+            ScriptCompletion::Batch(self.completion.clone()),
+        ));
+        Ok(())
+    }
+
+    pub fn scripts(&self) -> &[ScriptToEvaluate] {
+        self.scripts.as_slice()
+    }
+
+    pub fn is_submitted(&self) -> bool {
+        self.completion.is_submitted.load(Ordering::SeqCst)
+    }
+
+    /// Must be called once all scripts are evaluated, an empty batch signals right away
+    pub fn submit(&self) -> value_box::Result<()> {
+        if self.completion.is_submitted.swap(true, Ordering::SeqCst) {
+            return Err(anyhow!("The batch is already submitted").into());
+        }
+        self.completion.script_settled();
+        Ok(())
+    }
+
+    fn script_at(&self, index: usize) -> value_box::Result<&ScriptToEvaluate> {
+        self.scripts
+            .get(index)
+            .ok_or_else(|| anyhow!("No script at index {}", index).into())
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn webview_script_batch_new(
+    semaphore_index: usize,
+    semaphore_signaller: unsafe extern "C" fn(usize),
+) -> *mut ValueBox<ScriptBatch> {
+    ValueBox::new(ScriptBatch {
+        scripts: vec![],
+        completion: Arc::new(ScriptBatchCompletion {
+            remaining: AtomicUsize::new(1),
+            is_submitted: AtomicBool::new(false),
+            semaphore_index,
+            semaphore_signaller,
+        }),
+    })
+    .into_raw()
+}
+
+#[no_mangle]
+pub extern "C" fn webview_script_batch_add(
+    batch: *mut ValueBox<ScriptBatch>,
+    script: *mut ValueBox<StringBox>,
+) -> bool {
+    batch
+        .with_mut(|batch| script.with_ref(|script| batch.add(script.as_str())))
+        .map(|_| true)
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_script_batch_get_size(batch: *mut ValueBox<ScriptBatch>) -> usize {
+    batch.with_ref_ok(|batch| batch.scripts().len()).or_log(0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_script_batch_get_pending_count(
+    batch: *mut ValueBox<ScriptBatch>,
+) -> usize {
+    batch
+        .with_ref_ok(|batch| {
+            batch
+                .scripts()
+                .iter()
+                .filter(|script| script.is_pending())
+                .count()
+        })
+        .or_log(0)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_script_batch_get_result_at(
+    batch: *mut ValueBox<ScriptBatch>,
+    index: usize,
+    result: *mut ValueBox<StringBox>,
+) -> ScriptEvaluationState {
+    batch
+        .with_ref(|batch| {
+            let script = batch.script_at(index)?;
+            result.with_mut_ok(|result| {
+                let (value, state) = script.value_and_state();
+                result.set_string(value);
+                state
+            })
+        })
+        .or_log(ScriptEvaluationState::Released)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_script_batch_get_json_value_at(
+    batch: *mut ValueBox<ScriptBatch>,
+    index: usize,
+) -> *mut ValueBox<JsonValue> {
+    batch
+        .with_ref(|batch| {
+            batch
+                .script_at(index)?
+                .json_value()
+                .map(|json| ValueBox::new(json))
+                .ok_or_else(|| anyhow!("The script did not finish with a json value").into())
+        })
+        .into_raw()
+}
+
+/// Cancels the scripts that are still pending
+#[no_mangle]
+pub extern "C" fn webview_script_batch_cancel(batch: *mut ValueBox<ScriptBatch>) {
+    batch
+        .with_ref_ok(|batch| {
+            for script in batch.scripts() {
+                script.cancel();
+            }
+        })
+        .log();
+}
+
+#[no_mangle]
+pub extern "C" fn webview_script_batch_release(batch: *mut ValueBox<ScriptBatch>) {
+    batch.release();
+}
@@ -0,0 +1,201 @@
+use crate::script::ScriptToEvaluate;
+use regex::Regex;
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+
+const RESULT_KEY: &str = "__libwebview_result";
+const EXCEPTION_KEY: &str = "__libwebview_exception";
+const EVAL_BLOCKED_KEY: &str = "__libwebview_eval_blocked";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvalState {
+    Unknown,
+    Allowed,
+    Blocked,
+}
+
+/// Whether the page lets wrapped scripts use `eval`. Probing triggers a Content Security
+/// Policy violation on pages that block it, so it is done once per page load
+#[derive(Debug, Clone, Default)]
+pub struct EvalSupport(Arc<AtomicU64>);
+
+impl EvalSupport {
+    /// the page load count is kept in the upper bits, the state in the lower two
+    const STATE_BITS: u64 = 2;
+
+    pub fn page_started(&self) {
+        let _ = self
+            .0
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |value| {
+                Some(((value >> Self::STATE_BITS) + 1) << Self::STATE_BITS)
+            });
+    }
+
+    /// Returns the page load, to be passed to `set`, and what is known about its page
+    pub fn get(&self) -> (u64, EvalState) {
+        let value = self.0.load(Ordering::SeqCst);
+        let state = match value & ((1 << Self::STATE_BITS) - 1) {
+            1 => EvalState::Allowed,
+            2 => EvalState::Blocked,
+            _ => EvalState::Unknown,
+        };
+        (value >> Self::STATE_BITS, state)
+    }
+
+    /// Ignored once another page started loading
+    pub fn set(&self, page_load: u64, is_blocked: bool) {
+        let unknown = page_load << Self::STATE_BITS;
+        let known = unknown | if is_blocked { 2 } else { 1 };
+        let _ = self
+            .0
+            .compare_exchange(unknown, known, Ordering::SeqCst, Ordering::SeqCst);
+    }
+}
+
+/// A javascript statement returning a marker when the Content Security Policy of the page
+/// does not allow `eval`, then the script must be evaluated without a wrapper
+pub fn eval_blocked_check() -> String {
+    format!(
+        r#"try {{
+        (0, eval)("0");
+    }} catch (error) {{
+        return {{ {key}: true }};
+    }}"#,
+        key = EVAL_BLOCKED_KEY
+    )
+}
+
+/// Returns true if a wrapper returned the marker of `eval_blocked_check`
+pub fn is_eval_blocked(json: &str) -> bool {
+    json.contains(EVAL_BLOCKED_KEY)
+        && serde_json::from_str::<Value>(json)
+            .map(|outcome| outcome.get(EVAL_BLOCKED_KEY) == Some(&Value::Bool(true)))
+            .unwrap_or(false)
+}
+
+/// A javascript function describing a thrown value as a json object
+pub const DESCRIBE_EXCEPTION_FUNCTION: &str = r#"function (error) {
+    var isError = error instanceof Error;
+    var message = isError ? error.message : error;
+    if (typeof message !== "string") {
+        try {
+            message = JSON.stringify(message);
+        } catch (serializationError) {
+            message = String(message);
+        }
+    }
+    return {
+        message: String(message),
+        name: isError ? String(error.name) : "",
+        line: isError ? (error.line || error.lineNumber || 0) : 0,
+        column: isError ? (error.column || error.columnNumber || 0) : 0,
+        stack: isError && error.stack ? String(error.stack) : ""
+    };
+}"#;
+
+#[derive(Debug, Clone, Default)]
+pub struct ScriptException {
+    pub message: String,
+    pub name: String,
+    pub line: u32,
+    pub column: u32,
+    pub stack: String,
+}
+
+impl ScriptException {
+    pub fn from_json(json: &Value) -> Self {
+        let text = |key: &str| {
+            json.get(key)
+                .and_then(Value::as_str)
+                .unwrap_or("")
+                .to_string()
+        };
+        let number = |key: &str| {
+            json.get(key)
+                .and_then(Value::as_u64)
+                .map(|number| number as u32)
+                .unwrap_or(0)
+        };
+
+        let mut exception = Self {
+            message: text("message"),
+            name: text("name"),
+            line: number("line"),
+            column: number("column"),
+            stack: text("stack"),
+        };
+        // engines that do not expose the position on errors still mention it in the stack
+        if exception.line == 0 {
+            if let Some((line, column)) = stack_position(&exception.stack) {
+                exception.line = line;
+                exception.column = column;
+            }
+        }
+        exception
+    }
+
+    pub fn description(&self) -> String {
+        if self.name.is_empty() {
+            self.message.clone()
+        } else {
+            format!("{}: {}", self.name, self.message)
+        }
+    }
+}
+
+/// The line and column of the innermost frame of a stack trace
+fn stack_position(stack: &str) -> Option<(u32, u32)> {
+    static POSITION: OnceLock<Regex> = OnceLock::new();
+    let position = POSITION.get_or_init(|| Regex::new(r":(\d+):(\d+)\)?\s*$").unwrap());
+    stack.lines().find_map(|frame| {
+        position.captures(frame).and_then(|captures| {
+            Some((
+                captures.get(1)?.as_str().parse().ok()?,
+                captures.get(2)?.as_str().parse().ok()?,
+            ))
+        })
+    })
+}
+
+/// Evaluates the script globally and returns either its value or the thrown exception,
+/// because webviews do not report exceptions of evaluated scripts reliably.
+/// Pages whose Content Security Policy lacks 'unsafe-eval' can not be wrapped,
+/// `is_probing` first checks whether the page allows `eval`
+pub fn catching_script(script: &str, is_probing: bool) -> String {
+    format!(
+        r#"(function () {{
+    {eval_blocked_check}
+    try {{
+        var value = (0, eval)({script});
+        return {{ {result_key}: value === undefined ? null : value }};
+    }} catch (error) {{
+        return {{ {exception_key}: ({describe})(error) }};
+    }}
+}})();"#,
+        script = Value::from(script),
+        eval_blocked_check = if is_probing {
+            eval_blocked_check()
+        } else {
+            String::new()
+        },
+        result_key = RESULT_KEY,
+        exception_key = EXCEPTION_KEY,
+        describe = DESCRIBE_EXCEPTION_FUNCTION
+    )
+}
+
+/// Settles the script with the json returned by a `catching_script`
+pub fn settle_caught(script: &ScriptToEvaluate, json: String) {
+    let Ok(outcome) = serde_json::from_str::<Value>(&json) else {
+        script.set_value(json);
+        return;
+    };
+    if let Some(exception) = outcome.get(EXCEPTION_KEY) {
+        script.set_exception(ScriptException::from_json(exception));
+    } else if let Some(value) = outcome.get(RESULT_KEY) {
+        script.set_value(value.to_string());
+    } else {
+        script.set_value(json);
+    }
+}
@@ -1,17 +1,69 @@
+use crate::async_script::AsyncScripts;
+use crate::attributes::WebViewAttributes;
 use crate::events_handler::{EventsHandler, WebViewId};
 use anyhow::anyhow;
 use raw_window_handle_extensions::VeryRawWindowHandle;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::error::Error;
+use std::ops::Deref;
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{AtomicU64, Ordering};
 use string_box::StringBox;
 use value_box::{ReturnBoxerResult, ValueBox, ValueBoxIntoRaw, ValueBoxPointer};
 use wry::dpi::{LogicalPosition, LogicalSize};
 use wry::raw_window_handle::{RawWindowHandle, WindowHandle};
-use wry::{Rect, WebView, WebViewAttributes, WebViewBuilder};
+use wry::{Rect, WebViewBuilder};
 
+use crate::page_messages::PageMessenger;
 use crate::script::ScriptToEvaluate;
+use crate::script_batch::ScriptBatch;
+use crate::script_exception::{
+    catching_script, is_eval_blocked, settle_caught, EvalState, EvalSupport,
+};
+
+thread_local! {
+    /// Script callbacks must be `Send`, so they find their webview by key on the main thread
+    static WEBVIEWS: RefCell<HashMap<u64, Weak<wry::WebView>>> = RefCell::new(HashMap::new());
+}
+
+static NEXT_WEBVIEW_KEY: AtomicU64 = AtomicU64::new(1);
+
+pub struct WebView {
+    key: u64,
+    webview: Rc<wry::WebView>,
+    page_messenger: PageMessenger,
+    async_scripts: AsyncScripts,
+    eval_support: EvalSupport,
+}
+
+impl WebView {
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+
+    pub fn eval_support(&self) -> EvalSupport {
+        self.eval_support.clone()
+    }
+}
+
+impl Drop for WebView {
+    fn drop(&mut self) {
+        WEBVIEWS.with(|webviews| webviews.borrow_mut().remove(&self.key));
+    }
+}
+
+impl Deref for WebView {
+    type Target = wry::WebView;
+
+    fn deref(&self) -> &Self::Target {
+        &self.webview
+    }
+}
 
 fn build(
-    attributes: *mut ValueBox<WebViewAttributes<'static>>,
+    attributes: *mut ValueBox<WebViewAttributes>,
     raw_window_handle: *mut VeryRawWindowHandle,
 ) -> value_box::Result<WebView> {
     let raw_window_handle = unsafe { VeryRawWindowHandle::from_ptr(raw_window_handle) }
@@ -23,10 +75,19 @@ fn build(
 
     let window_handle = unsafe { WindowHandle::borrow_raw(raw_window_handle) };
 
-    let builder = attributes.take_value().map(|mut attributes| {
+    let attributes = attributes.take_value()?;
+    #[allow(unused_variables)]
+    let navigation_decider = attributes.host_navigation_decider();
+    let same_webview_loader = attributes.same_webview_loader();
+    let page_messenger = attributes.page_messenger();
+    let async_scripts = attributes.async_scripts();
+    let eval_support = attributes.eval_support();
+
+    let builder = {
+        let mut attributes = attributes.into_wry_attributes();
         attributes.devtools = true;
         WebViewBuilder::with_attributes(attributes)
-    })?;
+    };
 
     let webview = {
         #[cfg(any(
@@ -48,20 +109,37 @@ fn build(
         )))]
         {
             use gtk::prelude::*;
-            use wry::WebViewBuilderExtUnix;
+            use wry::{WebViewBuilderExtUnix, WebViewExtUnix};
 
             let fixed = gtk::Fixed::new();
             fixed.show_all();
 
-            builder.build_gtk(&fixed).map_err(|error| anyhow!(error))?
+            let webview = builder.build_gtk(&fixed).map_err(|error| anyhow!(error))?;
+            if let Some(navigation_decider) = navigation_decider {
+                navigation_decider.connect(&webview.webview());
+            }
+            webview
         }
     };
-    Ok(webview)
+    let webview = Rc::new(webview);
+    same_webview_loader.attach(&webview);
+    page_messenger.attach(&webview);
+
+    let key = NEXT_WEBVIEW_KEY.fetch_add(1, Ordering::Relaxed);
+    WEBVIEWS.with(|webviews| webviews.borrow_mut().insert(key, Rc::downgrade(&webview)));
+
+    Ok(WebView {
+        key,
+        webview,
+        page_messenger,
+        async_scripts,
+        eval_support,
+    })
 }
 
 #[no_mangle]
 pub extern "C" fn webview_build(
-    attributes: *mut ValueBox<WebViewAttributes<'static>>,
+    attributes: *mut ValueBox<WebViewAttributes>,
     window_handle: *mut VeryRawWindowHandle,
 ) -> *mut ValueBox<WebView> {
     build(attributes, window_handle)
@@ -69,6 +147,73 @@ pub extern "C" fn webview_build(
         .into_raw()
 }
 
+/// Evaluates the script as is, once its wrapper found `eval` blocked by the Content Security
+/// Policy of the page. Exceptions are then not reported and a returned Promise is not awaited
+pub fn evaluate_script_without_eval(webview_key: u64, script: ScriptToEvaluate) {
+    let webview = WEBVIEWS.with(|webviews| {
+        webviews
+            .borrow()
+            .get(&webview_key)
+            .and_then(|webview| webview.upgrade())
+    });
+    let Some(webview) = webview else {
+        script.set_error("The webview is released".to_string());
+        return;
+    };
+    let script_clone = script.clone();
+    let _ = webview
+        .evaluate_script_with_callback(script.script(), move |value| {
+            script_clone.set_value(value);
+        })
+        .inspect_err(|error| {
+            script.set_error(error.to_string());
+        });
+}
+
+fn evaluate_script_with_result(webview: &WebView, script: &ScriptToEvaluate) -> wry::Result<()> {
+    let script_clone = script.clone();
+    webview
+        .evaluate_script_with_callback(script.script(), move |value| {
+            script_clone.set_value(value);
+        })
+        .inspect_err(|error| {
+            script.set_error(error.to_string());
+        })
+}
+
+fn evaluate_script_catching_exceptions(
+    webview: &WebView,
+    script: &ScriptToEvaluate,
+) -> wry::Result<()> {
+    let (page_load, eval_state) = webview.eval_support.get();
+    if eval_state == EvalState::Blocked {
+        return evaluate_script_with_result(webview, script);
+    }
+    let is_probing = eval_state == EvalState::Unknown;
+
+    let script_clone = script.clone();
+    let webview_key = webview.key();
+    let eval_support = webview.eval_support();
+    webview
+        .evaluate_script_with_callback(
+            &catching_script(script.script(), is_probing),
+            move |value| {
+                if is_eval_blocked(&value) {
+                    eval_support.set(page_load, true);
+                    evaluate_script_without_eval(webview_key, script_clone.clone());
+                    return;
+                }
+                if is_probing && !value.is_empty() {
+                    eval_support.set(page_load, false);
+                }
+                settle_caught(&script_clone, value);
+            },
+        )
+        .inspect_err(|error| {
+            script.set_error(error.to_string());
+        })
+}
+
 #[no_mangle]
 pub extern "C" fn webview_evaluate_script_with_result(
     webview: *mut ValueBox<WebView>,
@@ -77,11 +222,66 @@ pub extern "C" fn webview_evaluate_script_with_result(
     webview
         .with_ref(|webview| {
             script.with_ref(|script| {
-                let script_clone = script.clone();
+                evaluate_script_with_result(webview, script).map_err(|error| anyhow!(error).into())
+            })
+        })
+        .log();
+}
+
+/// Like `webview_evaluate_script_with_result`, but a thrown exception is reported as such.
+/// The script is evaluated with an indirect `eval`, so its top-level `let`, `const` and
+/// `class` declarations are not visible to later scripts. Pages whose Content Security Policy
+/// blocks `eval` get the script evaluated as is, without reporting exceptions
+#[no_mangle]
+pub extern "C" fn webview_evaluate_script_catching_exceptions(
+    webview: *mut ValueBox<WebView>,
+    script: *mut ValueBox<ScriptToEvaluate>,
+) {
+    webview
+        .with_ref(|webview| {
+            script.with_ref(|script| {
+                evaluate_script_catching_exceptions(webview, script)
+                    .map_err(|error| anyhow!(error).into())
+            })
+        })
+        .log();
+}
+
+/// Scripts that fail to start are `Errored`, the others are still evaluated
+#[no_mangle]
+pub extern "C" fn webview_evaluate_script_batch(
+    webview: *mut ValueBox<WebView>,
+    batch: *mut ValueBox<ScriptBatch>,
+) {
+    webview
+        .with_ref(|webview| {
+            batch.with_ref(|batch| {
+                // scripts of a submitted batch were already evaluated
+                if batch.is_submitted() {
+                    return Err(anyhow!("The batch is already submitted").into());
+                }
+                for script in batch.scripts() {
+                    let _ = evaluate_script_with_result(webview, script);
+                }
+                batch.submit()
+            })
+        })
+        .log();
+}
+
+/// Awaits the Promise returned by the script. The result is `Finished` with the resolved value
+/// or `Errored` with the rejection reason
+#[no_mangle]
+pub extern "C" fn webview_evaluate_async_script_with_result(
+    webview: *mut ValueBox<WebView>,
+    script: *mut ValueBox<ScriptToEvaluate>,
+) {
+    webview
+        .with_ref(|webview| {
+            script.with_ref(|script| {
                 webview
-                    .evaluate_script_with_callback(script.script(), move |value| {
-                        script_clone.set_value(value);
-                    })
+                    .async_scripts
+                    .evaluate(webview, script)
                     .inspect_err(|error| {
                         script.set_error(error.to_string());
                     })
@@ -91,6 +291,37 @@ pub extern "C" fn webview_evaluate_script_with_result(
         .log();
 }
 
+/// Messages posted before the page finished loading are delivered once it has
+#[no_mangle]
+pub extern "C" fn webview_post_message(
+    webview: *mut ValueBox<WebView>,
+    channel: *mut ValueBox<StringBox>,
+    json: *mut ValueBox<StringBox>,
+) -> bool {
+    webview
+        .with_ref(|webview| {
+            channel.with_ref(|channel| {
+                json.with_ref(|json| {
+                    let data = serde_json::from_str::<Value>(json.as_str())
+                        .map_err(|error| anyhow!(error))?;
+                    webview
+                        .page_messenger
+                        .post(channel.as_str(), &data)
+                        .map_err(|error| anyhow!(error).into())
+                        .map(|_| true)
+                })
+            })
+        })
+        .or_log(false)
+}
+
+#[no_mangle]
+pub extern "C" fn webview_get_pending_messages_count(webview: *mut ValueBox<WebView>) -> usize {
+    webview
+        .with_ref_ok(|webview| webview.page_messenger.pending_count())
+        .or_log(0)
+}
+
 #[no_mangle]
 pub extern "C" fn webview_set_visible(webview: *mut ValueBox<WebView>, is_visible: bool) {
     webview